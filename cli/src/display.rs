@@ -0,0 +1,164 @@
+//! Human readable rendering of xbooth accounts
+use xbooth::state::{AccountType, Payload, XboothAccount};
+
+/// Render the payload as hex, followed by the text if it is valid utf8
+fn format_payload(payload: &Payload) -> String {
+    match &payload.utf8 {
+        Some(text) if !text.is_empty() => {
+            format!(
+                "({} bytes): {}\n  text: {:?}",
+                payload.len, payload.hex, text
+            )
+        }
+        _ => format!("({} bytes): {}", payload.len, payload.hex),
+    }
+}
+
+/// Render a decoded account owned by the echo program
+pub fn format_account(account: &XboothAccount) -> String {
+    match account {
+        XboothAccount::AuthorizedBuffer {
+            version,
+            buffer_kind,
+            authority,
+            seed_authority,
+            buffer_seed,
+            bump_seed,
+            pending_authority,
+            threshold,
+            signers,
+            write_seq,
+            last_writer,
+            last_write_slot,
+            last_write_timestamp,
+            last_write_len,
+            frozen,
+            delegate_count,
+            payload,
+            ..
+        } => {
+            let mut out = format!(
+                "authorized buffer\n  kind: {}\n  authority: {}\n  seed authority: {}\n  buffer seed: {}\n  bump seed: {}\n  write seq: {}",
+                buffer_kind, authority, seed_authority, buffer_seed, bump_seed, write_seq
+            );
+            let current_version = AccountType::AuthorizedBuffer.current_version();
+            if *version < current_version {
+                out += &format!(
+                    "\n  layout version: {}, migrate to upgrade to {}",
+                    version, current_version
+                );
+            }
+            if let Some(pending_authority) = pending_authority {
+                out += &format!("\n  pending authority: {}", pending_authority);
+            }
+            if *frozen {
+                out += "\n  frozen";
+            }
+            if *write_seq > 0 {
+                out += &format!(
+                    "\n  last write: {} bytes by {} in slot {} at {}",
+                    last_write_len, last_writer, last_write_slot, last_write_timestamp
+                );
+            }
+            if *delegate_count > 0 {
+                out += &format!("\n  delegates: {}", delegate_count);
+            }
+            if *threshold > 0 {
+                out += &format!(
+                    "\n  multisig: {} of {}\n    {}",
+                    threshold,
+                    signers.len(),
+                    signers.join("\n    ")
+                );
+            }
+            out + &format!("\n  payload {}", format_payload(payload))
+        }
+        XboothAccount::VendingMachineBuffer {
+            price,
+            bump_seed,
+            payload,
+            ..
+        } => format!(
+            "vending machine buffer\n  price: {}\n  bump seed: {}\n  payload {}",
+            price,
+            bump_seed,
+            format_payload(payload)
+        ),
+        XboothAccount::ConsumerCursor {
+            buffer,
+            consumer,
+            cursor,
+            ..
+        } => format!(
+            "consumer cursor\n  buffer: {}\n  consumer: {}\n  cursor: {}",
+            buffer, consumer, cursor
+        ),
+        XboothAccount::DelegateRecord {
+            buffer,
+            delegate,
+            expires_at_slot,
+            remaining_writes,
+            authority_generation,
+            ..
+        } => format!(
+            "delegate record\n  buffer: {}\n  delegate: {}\n  expires at slot: {}\n  remaining writes: {}\n  authority generation: {}",
+            buffer, delegate, expires_at_slot, remaining_writes, authority_generation
+        ),
+        XboothAccount::WriteProposal {
+            buffer,
+            proposer,
+            data_hash,
+            expires_at_slot,
+            approvals,
+            payload,
+            ..
+        } => format!(
+            "write proposal\n  buffer: {}\n  proposer: {}\n  data hash: {}\n  expires at slot: {}\n  approvals: {}\n    {}\n  data {}",
+            buffer,
+            proposer,
+            data_hash,
+            expires_at_slot,
+            approvals.len(),
+            approvals.join("\n    "),
+            format_payload(payload)
+        ),
+        XboothAccount::EchoBuffer { payload, .. } => {
+            format!("echo buffer\n  payload {}", format_payload(payload))
+        }
+        XboothAccount::Unknown { data } => {
+            format!("unknown account\n  data {}", format_payload(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+    use xbooth::state::{
+        decode_account, AuthorizedBufferHeader, BufferKind, AUTH_BUFFER_HEADER_SIZE,
+    };
+
+    #[test]
+    fn test_format_authorized_buffer() {
+        let authority = Pubkey::new_unique();
+        let mut data = vec![0; AUTH_BUFFER_HEADER_SIZE + 4];
+        AuthorizedBufferHeader::new(255, 7, BufferKind::Append, authority)
+            .pack(&mut data)
+            .unwrap();
+        data[AUTH_BUFFER_HEADER_SIZE..].copy_from_slice(b"hi\0\0");
+
+        let out = format_account(&decode_account(&data));
+        assert!(out.starts_with("authorized buffer\n  kind: Append"));
+        assert!(out.contains(&format!("authority: {}", authority)));
+        assert!(out.contains("payload (4 bytes): 68690000\n  text: \"hi\""));
+    }
+
+    #[test]
+    fn test_format_unknown_account() {
+        assert_eq!(
+            format_account(&decode_account(&[0xff, 0x01])),
+            "unknown account\n  data (2 bytes): ff01"
+        );
+    }
+}
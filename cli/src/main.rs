@@ -0,0 +1,567 @@
+use std::{process, str::FromStr};
+
+use clap::{
+    crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use xbooth::{
+    instruction::{self, EchoMode},
+    state::{
+        decode_account, find_authorized_buffer_address, find_vending_machine_address, BufferKind,
+        ECHO_BUFFER_HEADER_SIZE,
+    },
+};
+
+mod display;
+
+type Error = Box<dyn std::error::Error>;
+
+struct Config {
+    rpc_client: RpcClient,
+    payer: Keypair,
+    program_id: Pubkey,
+}
+
+fn pubkey_arg(matches: &ArgMatches, name: &str) -> Result<Pubkey, Error> {
+    let value = matches
+        .value_of(name)
+        .ok_or_else(|| format!("missing {}", name))?;
+    Pubkey::from_str(value).map_err(|err| format!("invalid {} {}: {}", name, value, err).into())
+}
+
+fn parse_arg<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<T, Error>
+where
+    T::Err: std::fmt::Display,
+{
+    let value = matches.value_of(name).unwrap();
+    value
+        .parse()
+        .map_err(|err| format!("invalid {} {}: {}", name, value, err).into())
+}
+
+fn parse_buffer_kind(value: &str) -> Result<BufferKind, Error> {
+    match value {
+        "overwrite" => Ok(BufferKind::Overwrite),
+        "append" => Ok(BufferKind::Append),
+        "ring" => Ok(BufferKind::Ring),
+        _ => Err(format!("invalid buffer kind {}", value).into()),
+    }
+}
+
+fn parse_echo_mode(value: &str) -> Result<EchoMode, Error> {
+    match value {
+        "truncate" => Ok(EchoMode::Truncate),
+        "zero-pad" => Ok(EchoMode::ZeroPad),
+        "strict" => Ok(EchoMode::Strict),
+        _ => Err(format!("invalid echo mode {}", value).into()),
+    }
+}
+
+fn send(config: &Config, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), Error> {
+    let mut all_signers = vec![&config.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&config.payer.pubkey()),
+        &all_signers,
+        config.rpc_client.get_latest_blockhash()?,
+    );
+    let signature = config
+        .rpc_client
+        .send_and_confirm_transaction(&transaction)?;
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+fn command_echo(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let data = matches.value_of("data").unwrap().as_bytes().to_vec();
+    let mode = parse_echo_mode(matches.value_of("mode").unwrap())?;
+    match matches.value_of("buffer") {
+        Some(_) => {
+            let buffer = pubkey_arg(matches, "buffer")?;
+            send(
+                config,
+                &[instruction::echo(&config.program_id, &buffer, data, mode)],
+                &[],
+            )
+        }
+        None => {
+            let buffer = Keypair::new();
+            println!("Buffer: {}", buffer.pubkey());
+            send(
+                config,
+                &[
+                    instruction::initialize_echo_buffer(
+                        &config.program_id,
+                        &buffer.pubkey(),
+                        &config.payer.pubkey(),
+                        ECHO_BUFFER_HEADER_SIZE + data.len(),
+                    ),
+                    instruction::echo(&config.program_id, &buffer.pubkey(), data, mode),
+                ],
+                &[&buffer],
+            )
+        }
+    }
+}
+
+/// returns the buffer address and the instruction creating it
+fn init_authorized_instruction(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    matches: &ArgMatches,
+) -> Result<(Pubkey, Instruction), Error> {
+    let buffer_seed = parse_arg(matches, "seed")?;
+    let buffer_size = parse_arg(matches, "size")?;
+    let buffer_kind = parse_buffer_kind(matches.value_of("kind").unwrap())?;
+    let (buffer, _) = find_authorized_buffer_address(program_id, authority, buffer_seed);
+    Ok((
+        buffer,
+        instruction::initialize_authorized_echo(
+            program_id,
+            authority,
+            buffer_seed,
+            buffer_size,
+            buffer_kind,
+        ),
+    ))
+}
+
+fn command_init_authorized(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let (buffer, instruction) =
+        init_authorized_instruction(&config.program_id, &config.payer.pubkey(), matches)?;
+    println!("Buffer: {}", buffer);
+    send(config, &[instruction], &[])
+}
+
+fn authorized_echo_instruction(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    matches: &ArgMatches,
+) -> Result<Instruction, Error> {
+    let data = matches.value_of("data").unwrap().as_bytes().to_vec();
+    let buffer = match matches.value_of("buffer") {
+        Some(_) => pubkey_arg(matches, "buffer")?,
+        None => {
+            let buffer_seed = parse_arg(matches, "seed")?;
+            find_authorized_buffer_address(program_id, authority, buffer_seed).0
+        }
+    };
+    Ok(instruction::authorized_echo(
+        program_id, &buffer, authority, data,
+    ))
+}
+
+fn command_authorized_echo(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let instruction =
+        authorized_echo_instruction(&config.program_id, &config.payer.pubkey(), matches)?;
+    send(config, &[instruction], &[])
+}
+
+/// returns the buffer address and the instruction creating it
+fn init_vending_machine_instruction(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    matches: &ArgMatches,
+) -> Result<(Pubkey, Instruction), Error> {
+    let mint = pubkey_arg(matches, "mint")?;
+    let price = parse_arg(matches, "price")?;
+    let buffer_size = parse_arg(matches, "size")?;
+    let (buffer, _) = find_vending_machine_address(program_id, &mint, price);
+    Ok((
+        buffer,
+        instruction::initialize_vending_machine(program_id, &mint, payer, price, buffer_size),
+    ))
+}
+
+fn command_init_vending_machine(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let (buffer, instruction) =
+        init_vending_machine_instruction(&config.program_id, &config.payer.pubkey(), matches)?;
+    println!("Buffer: {}", buffer);
+    send(config, &[instruction], &[])
+}
+
+/// Decode an account of the echo program for printing
+fn format_show(
+    address: &Pubkey,
+    account: &Account,
+    program_id: &Pubkey,
+    json: bool,
+) -> Result<String, Error> {
+    if account.owner != *program_id {
+        return Err(format!(
+            "{} is owned by {}, not by the echo program {}",
+            address, account.owner, program_id
+        )
+        .into());
+    }
+    let decoded = decode_account(&account.data);
+    let body = if json {
+        decoded.to_json()
+    } else {
+        display::format_account(&decoded)
+    };
+    Ok(format!("{}\n{}", address, body))
+}
+
+fn command_show(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    matches: &ArgMatches,
+) -> Result<(), Error> {
+    let address = pubkey_arg(matches, "address")?;
+    let account = rpc_client.get_account(&address)?;
+    println!(
+        "{}",
+        format_show(&address, &account, program_id, matches.is_present("json"))?
+    );
+    Ok(())
+}
+
+fn app<'a, 'b>() -> App<'a, 'b> {
+    let seed_arg = Arg::with_name("seed")
+        .long("seed")
+        .value_name("SEED")
+        .takes_value(true)
+        .default_value("0")
+        .help("Seed the authorized buffer address is derived from");
+    let size_arg = Arg::with_name("size")
+        .long("size")
+        .value_name("BYTES")
+        .takes_value(true)
+        .required(true)
+        .help("Buffer size in bytes, including the header");
+    let data_arg = Arg::with_name("data")
+        .value_name("DATA")
+        .required(true)
+        .index(1)
+        .help("Data to write, as utf8 text");
+
+    App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .short("u")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .default_value("http://localhost:8899")
+                .help("JSON RPC URL of the cluster"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .long("keypair")
+                .short("k")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Fee payer and authority keypair [default: ~/.config/solana/id.json]"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .long("program-id")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .global(true)
+                .help("Address of the deployed echo program"),
+        )
+        .subcommand(
+            SubCommand::with_name("echo")
+                .about("Echo data into a buffer, creating a new buffer unless one is given")
+                .arg(data_arg.clone())
+                .arg(
+                    Arg::with_name("buffer")
+                        .long("buffer")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .help("Existing echo buffer to write to"),
+                )
+                .arg(
+                    Arg::with_name("mode")
+                        .long("mode")
+                        .value_name("MODE")
+                        .takes_value(true)
+                        .possible_values(&["truncate", "zero-pad", "strict"])
+                        .default_value("zero-pad")
+                        .help("How data that does not match the buffer length is written"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("init-authorized")
+                .about("Create an authorized buffer owned by the keypair")
+                .arg(seed_arg.clone())
+                .arg(size_arg.clone())
+                .arg(
+                    Arg::with_name("kind")
+                        .long("kind")
+                        .value_name("KIND")
+                        .takes_value(true)
+                        .possible_values(&["overwrite", "append", "ring"])
+                        .default_value("overwrite")
+                        .help("How writes are stored in the buffer"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("authorized-echo")
+                .about("Write data to an authorized buffer as the keypair")
+                .arg(seed_arg)
+                .arg(data_arg)
+                .arg(
+                    Arg::with_name("buffer")
+                        .long("buffer")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .help("Authorized buffer to write to instead of the one derived from the seed"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("init-vending-machine")
+                .about("Create a vending machine buffer that burns tokens of a mint per write")
+                .arg(
+                    Arg::with_name("mint")
+                        .long("mint")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Mint of the tokens burned per write"),
+                )
+                .arg(
+                    Arg::with_name("price")
+                        .long("price")
+                        .value_name("AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Tokens burned per write"),
+                )
+                .arg(size_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Decode and print a buffer")
+                .arg(
+                    Arg::with_name("address")
+                        .value_name("ADDRESS")
+                        .required(true)
+                        .index(1)
+                        .help("Address of the buffer"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print the decoded account as json"),
+                ),
+        )
+}
+
+fn main() {
+    let matches = app().get_matches();
+    let (command, sub_matches) = matches.subcommand();
+    let sub_matches = sub_matches.unwrap();
+    let result = (|| -> Result<(), Error> {
+        let rpc_client = RpcClient::new_with_commitment(
+            sub_matches.value_of("url").unwrap().to_string(),
+            CommitmentConfig::confirmed(),
+        );
+        let program_id = pubkey_arg(sub_matches, "program_id")?;
+        if command == "show" {
+            return command_show(&rpc_client, &program_id, sub_matches);
+        }
+
+        let keypair_path = match sub_matches.value_of("keypair") {
+            Some(path) => path.to_string(),
+            None => format!(
+                "{}/.config/solana/id.json",
+                std::env::var("HOME").unwrap_or_default()
+            ),
+        };
+        let config = Config {
+            rpc_client,
+            payer: read_keypair_file(&keypair_path)
+                .map_err(|err| format!("failed to read keypair {}: {}", keypair_path, err))?,
+            program_id,
+        };
+        match command {
+            "echo" => command_echo(&config, sub_matches),
+            "init-authorized" => command_init_authorized(&config, sub_matches),
+            "authorized-echo" => command_authorized_echo(&config, sub_matches),
+            "init-vending-machine" => command_init_vending_machine(&config, sub_matches),
+            _ => unreachable!(),
+        }
+    })();
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use xbooth::state::EchoBufferHeader;
+
+    #[test]
+    fn test_parse_subcommands() {
+        let matches = app()
+            .get_matches_from_safe(vec![
+                "xbooth-cli",
+                "init-authorized",
+                "--size",
+                "128",
+                "--kind",
+                "ring",
+                "--program-id",
+                "11111111111111111111111111111111",
+            ])
+            .unwrap();
+        let (command, sub_matches) = matches.subcommand();
+        let sub_matches = sub_matches.unwrap();
+        assert_eq!(command, "init-authorized");
+        assert_eq!(parse_arg::<usize>(sub_matches, "size").unwrap(), 128);
+        assert_eq!(parse_arg::<u64>(sub_matches, "seed").unwrap(), 0);
+        assert_eq!(
+            parse_buffer_kind(sub_matches.value_of("kind").unwrap()).unwrap(),
+            BufferKind::Ring
+        );
+        assert_eq!(
+            pubkey_arg(sub_matches, "program_id").unwrap(),
+            Pubkey::default()
+        );
+
+        assert!(app()
+            .get_matches_from_safe(vec!["xbooth-cli", "show"])
+            .is_err());
+    }
+
+    fn sub_matches(args: &[&str]) -> ArgMatches<'static> {
+        let mut argv = vec!["xbooth-cli"];
+        argv.extend_from_slice(args);
+        let matches = app().get_matches_from_safe(argv).unwrap();
+        matches.subcommand_matches(args[0]).unwrap().clone()
+    }
+
+    #[test]
+    fn test_init_authorized_instruction() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let matches = sub_matches(&[
+            "init-authorized",
+            "--seed",
+            "7",
+            "--size",
+            "128",
+            "--kind",
+            "append",
+        ]);
+        let (buffer, built) =
+            init_authorized_instruction(&program_id, &authority, &matches).unwrap();
+        assert_eq!(
+            buffer,
+            find_authorized_buffer_address(&program_id, &authority, 7).0
+        );
+        assert_eq!(
+            built,
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority,
+                7,
+                128,
+                BufferKind::Append
+            )
+        );
+    }
+
+    #[test]
+    fn test_authorized_echo_instruction() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let matches = sub_matches(&["authorized-echo", "--seed", "3", "hello"]);
+        let built = authorized_echo_instruction(&program_id, &authority, &matches).unwrap();
+        let (buffer, _) = find_authorized_buffer_address(&program_id, &authority, 3);
+        assert_eq!(
+            built,
+            instruction::authorized_echo(&program_id, &buffer, &authority, b"hello".to_vec())
+        );
+
+        // buffers the keypair writes to as a delegate or co-signer are given explicitly
+        let buffer = Pubkey::new_unique();
+        let matches = sub_matches(&["authorized-echo", "--buffer", &buffer.to_string(), "hi"]);
+        let built = authorized_echo_instruction(&program_id, &authority, &matches).unwrap();
+        assert_eq!(
+            built,
+            instruction::authorized_echo(&program_id, &buffer, &authority, b"hi".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_init_vending_machine_instruction() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let matches = sub_matches(&[
+            "init-vending-machine",
+            "--mint",
+            &mint.to_string(),
+            "--price",
+            "5",
+            "--size",
+            "64",
+        ]);
+        let (buffer, built) =
+            init_vending_machine_instruction(&program_id, &payer, &matches).unwrap();
+        assert_eq!(
+            buffer,
+            find_vending_machine_address(&program_id, &mint, 5).0
+        );
+        assert_eq!(
+            built,
+            instruction::initialize_vending_machine(&program_id, &mint, &payer, 5, 64)
+        );
+
+        let matches = sub_matches(&[
+            "init-vending-machine",
+            "--mint",
+            "not-a-pubkey",
+            "--price",
+            "5",
+            "--size",
+            "64",
+        ]);
+        assert!(init_vending_machine_instruction(&program_id, &payer, &matches).is_err());
+    }
+
+    #[test]
+    fn test_format_show() {
+        let program_id = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let mut data = vec![0; ECHO_BUFFER_HEADER_SIZE + 2];
+        EchoBufferHeader::new().pack(&mut data).unwrap();
+        data[ECHO_BUFFER_HEADER_SIZE..].copy_from_slice(b"hi");
+        let mut account = Account::new(1, data.len(), &program_id);
+        account.data = data;
+
+        let decoded = decode_account(&account.data);
+        assert_eq!(
+            format_show(&address, &account, &program_id, false).unwrap(),
+            format!("{}\n{}", address, display::format_account(&decoded))
+        );
+        assert_eq!(
+            format_show(&address, &account, &program_id, true).unwrap(),
+            format!("{}\n{}", address, decoded.to_json())
+        );
+
+        account.owner = Pubkey::new_unique();
+        assert!(format_show(&address, &account, &program_id, false).is_err());
+    }
+}
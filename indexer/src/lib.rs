@@ -0,0 +1,336 @@
+//! Rebuild the write history of xbooth buffers from transaction log messages
+//!
+//! Transactions are read from `getTransaction` json results or built directly
+//! from the log messages of a local run, the events they contain are kept in
+//! an in-memory [`Index`] keyed by buffer address.
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+use xbooth::events::{decode_events, EchoEvent};
+
+/// Log messages of a successful transaction and where it landed
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionLogs {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// fee payer of the transaction, not necessarily the writer of its events
+    pub fee_payer: Option<Pubkey>,
+    pub logs: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcTransaction {
+    slot: u64,
+    block_time: Option<i64>,
+    transaction: RpcInnerTransaction,
+    meta: Option<RpcMeta>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcInnerTransaction {
+    signatures: Vec<String>,
+    message: RpcMessage,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcMessage {
+    account_keys: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcMeta {
+    err: Option<serde_json::Value>,
+    log_messages: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RpcTransactions {
+    One(RpcTransaction),
+    Many(Vec<RpcTransaction>),
+}
+
+/// Parse a json dump of one or an array of `getTransaction` results in `json` encoding
+///
+/// Failed transactions are skipped, their events were rolled back.
+pub fn load_transactions(json: &str) -> Result<Vec<TransactionLogs>, serde_json::Error> {
+    let transactions = match serde_json::from_str(json)? {
+        RpcTransactions::One(transaction) => vec![transaction],
+        RpcTransactions::Many(transactions) => transactions,
+    };
+    Ok(transactions
+        .into_iter()
+        .filter_map(|transaction| {
+            let meta = transaction.meta?;
+            if meta.err.is_some() {
+                return None;
+            }
+            Some(TransactionLogs {
+                signature: transaction
+                    .transaction
+                    .signatures
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+                slot: transaction.slot,
+                block_time: transaction.block_time,
+                fee_payer: transaction
+                    .transaction
+                    .message
+                    .account_keys
+                    .first()
+                    .and_then(|key| Pubkey::from_str(key).ok()),
+                logs: meta.log_messages.unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+/// Event of a buffer together with the transaction that emitted it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub fee_payer: Option<Pubkey>,
+    /// signer that wrote the data of a `DataWritten` event
+    pub writer: Option<Pubkey>,
+    pub event: EchoEvent,
+}
+
+/// Buffer the event belongs to
+pub fn event_buffer(event: &EchoEvent) -> Pubkey {
+    match event {
+        EchoEvent::BufferCreated { buffer, .. }
+        | EchoEvent::DataWritten { buffer, .. }
+        | EchoEvent::BufferClosed { buffer, .. }
+        | EchoEvent::VendingMachineInitialized { buffer, .. }
+        | EchoEvent::TokensBurned { buffer, .. }
+        | EchoEvent::EchoBufferCreated { buffer, .. }
+        | EchoEvent::DelegateAdded { buffer, .. }
+        | EchoEvent::DelegateRevoked { buffer, .. }
+        | EchoEvent::WriteProposed { buffer, .. }
+        | EchoEvent::WriteApproved { buffer, .. }
+        | EchoEvent::BufferFrozen { buffer }
+        | EchoEvent::AuthorityProposed { buffer, .. }
+        | EchoEvent::AuthorityChanged { buffer, .. }
+        | EchoEvent::BufferResized { buffer, .. }
+        | EchoEvent::ConsumerRegistered { buffer, .. }
+        | EchoEvent::Consumed { buffer, .. }
+        | EchoEvent::BufferMigrated { buffer, .. }
+        | EchoEvent::ConsumerClosed { buffer, .. } => *buffer,
+    }
+}
+
+/// In-memory history of every buffer of one xbooth deployment
+#[derive(Debug)]
+pub struct Index {
+    program_id: Pubkey,
+    buffers: HashMap<Pubkey, Vec<Entry>>,
+}
+
+impl Index {
+    pub fn new(program_id: Pubkey) -> Self {
+        Self {
+            program_id,
+            buffers: HashMap::new(),
+        }
+    }
+
+    /// Record the events of a transaction, transactions are expected in slot order
+    pub fn ingest(&mut self, transaction: &TransactionLogs) {
+        for event in decode_events(&self.program_id, &transaction.logs) {
+            self.buffers
+                .entry(event_buffer(&event))
+                .or_default()
+                .push(Entry {
+                    signature: transaction.signature.clone(),
+                    slot: transaction.slot,
+                    block_time: transaction.block_time,
+                    fee_payer: transaction.fee_payer,
+                    writer: match event {
+                        EchoEvent::DataWritten { signer, .. } => signer,
+                        _ => None,
+                    },
+                    event,
+                });
+        }
+    }
+
+    /// Addresses of all buffers seen so far
+    pub fn buffers(&self) -> Vec<Pubkey> {
+        let mut buffers: Vec<Pubkey> = self.buffers.keys().copied().collect();
+        buffers.sort();
+        buffers
+    }
+
+    /// Every event of `buffer`, oldest first
+    pub fn history(&self, buffer: &Pubkey) -> &[Entry] {
+        self.buffers.get(buffer).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Writes to `buffer`, oldest first
+    pub fn writes<'a>(&'a self, buffer: &Pubkey) -> impl Iterator<Item = &'a Entry> {
+        self.history(buffer)
+            .iter()
+            .filter(|entry| matches!(entry.event, EchoEvent::DataWritten { .. }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use borsh::BorshSerialize;
+    use xbooth::events::PROGRAM_DATA_PREFIX;
+
+    fn program_data(event: &EchoEvent) -> String {
+        format!(
+            "{}{}",
+            PROGRAM_DATA_PREFIX,
+            base64::encode(event.try_to_vec().unwrap())
+        )
+    }
+
+    fn transaction_logs(fee_payer: Pubkey, logs: Vec<String>) -> TransactionLogs {
+        TransactionLogs {
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: None,
+            fee_payer: Some(fee_payer),
+            logs,
+        }
+    }
+
+    #[test]
+    fn test_index_rpc_dump() {
+        let program_id = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let written = EchoEvent::data_written(&buffer, Some(&signer), 0, b"abc");
+        let closed = EchoEvent::BufferClosed {
+            buffer,
+            destination: signer,
+            lamports: 10,
+        };
+        let transaction = |slot: u64, err: serde_json::Value, event: &EchoEvent| {
+            serde_json::json!({
+                "slot": slot,
+                "blockTime": 1_650_000_000 + slot,
+                "transaction": {
+                    "signatures": [format!("sig{}", slot)],
+                    "message": { "accountKeys": [signer.to_string(), program_id.to_string()] },
+                },
+                "meta": {
+                    "err": err,
+                    "logMessages": [
+                        format!("Program {} invoke [1]", program_id),
+                        program_data(event),
+                        format!("Program {} success", program_id),
+                    ],
+                },
+            })
+        };
+        let dump = serde_json::json!([
+            transaction(1, serde_json::Value::Null, &written),
+            transaction(
+                2,
+                serde_json::json!({ "InstructionError": [0, "Custom"] }),
+                &closed
+            ),
+            transaction(3, serde_json::Value::Null, &closed),
+        ]);
+
+        let mut index = Index::new(program_id);
+        for transaction in load_transactions(&dump.to_string()).unwrap() {
+            index.ingest(&transaction);
+        }
+
+        assert_eq!(index.buffers(), vec![buffer]);
+        let history = index.history(&buffer);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].signature, "sig1");
+        assert_eq!(history[0].fee_payer, Some(signer));
+        assert_eq!(history[0].writer, Some(signer));
+        assert_eq!(history[0].event, written);
+        assert_eq!(history[1].slot, 3);
+        assert_eq!(index.writes(&buffer).count(), 1);
+    }
+
+    #[test]
+    fn test_index_cpi_writes() {
+        let program_id = Pubkey::new_unique();
+        let caller_program = Pubkey::new_unique();
+        let fee_payer = Pubkey::new_unique();
+        let authority_pda = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let direct = EchoEvent::data_written(&buffer, Some(&fee_payer), 0, b"abc");
+        let nested = EchoEvent::data_written(&buffer, Some(&authority_pda), 0, b"de");
+        let caller_data = EchoEvent::data_written(&buffer, Some(&fee_payer), 0, b"fg");
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            program_data(&direct),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", caller_program),
+            format!("Program {} invoke [2]", program_id),
+            program_data(&nested),
+            format!("Program {} success", program_id),
+            program_data(&caller_data),
+            format!("Program {} success", caller_program),
+        ];
+
+        let mut index = Index::new(program_id);
+        index.ingest(&transaction_logs(fee_payer, logs));
+
+        // the writer comes from the event, the fee payer did not sign the nested write
+        let writes: Vec<&Entry> = index.writes(&buffer).collect();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0].event, direct);
+        assert_eq!(writes[0].writer, Some(fee_payer));
+        assert_eq!(writes[1].event, nested);
+        assert_eq!(writes[1].writer, Some(authority_pda));
+        assert_eq!(writes[1].fee_payer, Some(fee_payer));
+    }
+
+    #[test]
+    fn test_index_skips_failed_transactions() {
+        let program_id = Pubkey::new_unique();
+        let fee_payer = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let written = EchoEvent::data_written(&buffer, Some(&fee_payer), 0, b"abc");
+        // the write succeeded but a later instruction failed, rolling it back
+        let dump = serde_json::json!({
+            "slot": 7,
+            "blockTime": null,
+            "transaction": {
+                "signatures": ["sig7"],
+                "message": { "accountKeys": [fee_payer.to_string(), program_id.to_string()] },
+            },
+            "meta": {
+                "err": { "InstructionError": [1, { "Custom": 37 }] },
+                "logMessages": [
+                    format!("Program {} invoke [1]", program_id),
+                    program_data(&written),
+                    format!("Program {} success", program_id),
+                    format!("Program {} invoke [1]", program_id),
+                    format!("Program {} failed: custom program error: 0x25", program_id),
+                ],
+            },
+        });
+
+        let transactions = load_transactions(&dump.to_string()).unwrap();
+        assert!(transactions.is_empty());
+        let mut index = Index::new(program_id);
+        for transaction in &transactions {
+            index.ingest(transaction);
+        }
+        assert!(index.buffers().is_empty());
+        assert_eq!(index.writes(&buffer).count(), 0);
+    }
+}
@@ -0,0 +1,60 @@
+use std::{env, fs, process, str::FromStr};
+
+use solana_program::pubkey::Pubkey;
+use xbooth::events::EchoEvent;
+use xbooth_indexer::{load_transactions, Index};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <program_id> <transactions.json>...", args[0]);
+        process::exit(1);
+    }
+    let program_id = Pubkey::from_str(&args[1]).unwrap_or_else(|err| {
+        eprintln!("invalid program id {}: {}", args[1], err);
+        process::exit(1);
+    });
+
+    let mut index = Index::new(program_id);
+    for path in &args[2..] {
+        let json = fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("failed to read {}: {}", path, err);
+            process::exit(1);
+        });
+        let transactions = load_transactions(&json).unwrap_or_else(|err| {
+            eprintln!("failed to parse {}: {}", path, err);
+            process::exit(1);
+        });
+        for transaction in &transactions {
+            index.ingest(transaction);
+        }
+    }
+
+    for buffer in index.buffers() {
+        println!("{}", buffer);
+        for entry in index.history(&buffer) {
+            let signer = entry
+                .writer
+                .or(entry.fee_payer)
+                .map(|signer| signer.to_string())
+                .unwrap_or_default();
+            match &entry.event {
+                EchoEvent::DataWritten {
+                    offset, len, hash, ..
+                } => println!(
+                    "  slot {} {} wrote {} bytes at {} hash {} ({})",
+                    entry.slot,
+                    signer,
+                    len,
+                    offset,
+                    solana_program::hash::Hash::new_from_array(*hash),
+                    entry.signature
+                ),
+                event => println!(
+                    "  slot {} {} {:?} ({})",
+                    entry.slot, signer, event, entry.signature
+                ),
+            }
+        }
+    }
+}
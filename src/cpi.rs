@@ -0,0 +1,194 @@
+//! Helpers for other on-chain programs to invoke the echo program
+//!
+//! Every helper takes the account infos of the instruction, including the echo
+//! program itself, and the signer seeds of any program derived signers,
+//! pass `&[]` when all signers signed the transaction.
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::{get_return_data, invoke, invoke_signed},
+    program_error::ProgramError,
+};
+
+use crate::error::EchoError;
+use crate::instruction;
+use crate::state::BufferKind;
+
+/// Accounts of `InitializeAuthorizedEcho`
+pub struct InitializeAuthorizedEcho<'info> {
+    pub echo_program: AccountInfo<'info>,
+    pub authorized_buffer: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+/// Accounts of `AuthorizedEcho` and `AuthorizedEchoAt`
+pub struct AuthorizedEcho<'info> {
+    pub echo_program: AccountInfo<'info>,
+    pub authorized_buffer: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+}
+
+/// Accounts of `ResizeAuthorizedBuffer`
+pub struct ResizeAuthorizedBuffer<'info> {
+    pub echo_program: AccountInfo<'info>,
+    pub authorized_buffer: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+/// Accounts of `CloseAuthorizedBuffer`
+pub struct CloseAuthorizedBuffer<'info> {
+    pub echo_program: AccountInfo<'info>,
+    pub authorized_buffer: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub destination: AccountInfo<'info>,
+}
+
+/// Accounts of `ReadBuffer`
+pub struct ReadBuffer<'info> {
+    pub echo_program: AccountInfo<'info>,
+    pub buffer: AccountInfo<'info>,
+}
+
+/// Create an authorized buffer derived from `authority` and `buffer_seed`
+pub fn initialize_authorized_echo(
+    ctx_accounts: InitializeAuthorizedEcho,
+    buffer_seed: u64,
+    buffer_size: usize,
+    buffer_kind: BufferKind,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::initialize_authorized_echo(
+        ctx_accounts.echo_program.key,
+        ctx_accounts.authority.key,
+        buffer_seed,
+        buffer_size,
+        buffer_kind,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            ctx_accounts.authorized_buffer,
+            ctx_accounts.authority,
+            ctx_accounts.system_program,
+            ctx_accounts.echo_program,
+        ],
+        signer_seeds,
+    )
+}
+
+/// Write `data` to an authorized buffer
+pub fn authorized_echo(
+    ctx_accounts: AuthorizedEcho,
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::authorized_echo(
+        ctx_accounts.echo_program.key,
+        ctx_accounts.authorized_buffer.key,
+        ctx_accounts.authority.key,
+        data,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            ctx_accounts.authorized_buffer,
+            ctx_accounts.authority,
+            ctx_accounts.echo_program,
+        ],
+        signer_seeds,
+    )
+}
+
+/// Write `data` at `offset` into the payload of an authorized buffer
+pub fn authorized_echo_at(
+    ctx_accounts: AuthorizedEcho,
+    offset: usize,
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::authorized_echo_at(
+        ctx_accounts.echo_program.key,
+        ctx_accounts.authorized_buffer.key,
+        ctx_accounts.authority.key,
+        offset,
+        data,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            ctx_accounts.authorized_buffer,
+            ctx_accounts.authority,
+            ctx_accounts.echo_program,
+        ],
+        signer_seeds,
+    )
+}
+
+/// Resize an authorized buffer to `new_size` bytes including the header
+pub fn resize_authorized_buffer(
+    ctx_accounts: ResizeAuthorizedBuffer,
+    new_size: usize,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::resize_authorized_buffer(
+        ctx_accounts.echo_program.key,
+        ctx_accounts.authorized_buffer.key,
+        ctx_accounts.authority.key,
+        new_size,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            ctx_accounts.authorized_buffer,
+            ctx_accounts.authority,
+            ctx_accounts.system_program,
+            ctx_accounts.echo_program,
+        ],
+        signer_seeds,
+    )
+}
+
+/// Close an authorized buffer, moving its lamports to `destination`
+pub fn close_authorized_buffer(
+    ctx_accounts: CloseAuthorizedBuffer,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::close_authorized_buffer(
+        ctx_accounts.echo_program.key,
+        ctx_accounts.authorized_buffer.key,
+        ctx_accounts.authority.key,
+        ctx_accounts.destination.key,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            ctx_accounts.authorized_buffer,
+            ctx_accounts.authority,
+            ctx_accounts.destination,
+            ctx_accounts.echo_program,
+        ],
+        signer_seeds,
+    )
+}
+
+/// Read `len` bytes of the buffer payload starting at `offset`,
+/// reads of 0 bytes return nothing without invoking the echo program
+pub fn read_buffer(
+    ctx_accounts: ReadBuffer,
+    offset: usize,
+    len: usize,
+) -> Result<Vec<u8>, ProgramError> {
+    // empty return data reads back as none
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let echo_program_id = *ctx_accounts.echo_program.key;
+    let ix = instruction::read_buffer(&echo_program_id, ctx_accounts.buffer.key, offset, len);
+    invoke(&ix, &[ctx_accounts.buffer, ctx_accounts.echo_program])?;
+    match get_return_data() {
+        Some((program_id, data)) if program_id == echo_program_id => Ok(data),
+        _ => Err(EchoError::InvalidReturnData.into()),
+    }
+}
@@ -1,9 +1,9 @@
-use crate::processor::Processor;
-use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg, pubkey::Pubkey};
+use crate::{error::EchoError, processor::Processor};
+use solana_program::{
+    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, msg,
+    program_error::PrintProgramError, pubkey::Pubkey,
+};
 
-#[cfg(not(feature = "no-entrypoint"))]
-use solana_program::entrypoint;
-#[cfg(not(feature = "no-entrypoint"))]
 entrypoint!(process_instruction);
 
 fn process_instruction(
@@ -17,5 +17,9 @@ fn process_instruction(
         accounts.len(),
         instruction_data
     );
-    Processor::process_instruction(program_id, accounts, instruction_data)
+    if let Err(error) = Processor::process_instruction(program_id, accounts, instruction_data) {
+        error.print::<EchoError>();
+        return Err(error);
+    }
+    Ok(())
 }
@@ -0,0 +1,200 @@
+use num_derive::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError,
+    msg,
+    program_error::{PrintProgramError, ProgramError},
+};
+use thiserror::Error;
+
+/// Errors returned by the echo program
+///
+/// The discriminant of each variant is its `ProgramError::Custom` code,
+/// new variants must be appended to keep existing codes stable.
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum EchoError {
+    // 0
+    /// Instruction data could not be deserialized
+    #[error("Invalid instruction")]
+    InvalidInstruction,
+    // 1
+    /// Buffer account does not match the expected program derived address
+    #[error("Buffer is not the expected program derived address")]
+    InvalidBufferPda,
+    // 2
+    /// Buffer is too small to hold a header and data
+    #[error("Buffer is too small")]
+    BufferTooSmall,
+    // 3
+    /// Buffer header could not be deserialized
+    #[error("Invalid buffer header")]
+    InvalidBufferHeader,
+    // 4
+    /// A required signature is missing
+    #[error("Missing required signer")]
+    MissingSigner,
+    // 5
+    /// Account is not owned by the expected program
+    #[error("Incorrect account owner")]
+    IncorrectOwner,
+    // 6
+    /// Account is not the expected token program
+    #[error("Incorrect token program")]
+    IncorrectTokenProgram,
+    // 7
+    /// Mint does not match the vending machine mint
+    #[error("Invalid mint")]
+    InvalidMint,
+    // 8
+    /// Token account could not be unpacked
+    #[error("Invalid token account")]
+    InvalidTokenAccount,
+    // 9
+    /// Token account holds less than the vending machine price
+    #[error("Insufficient tokens")]
+    InsufficientTokens,
+    // 10
+    /// Echo data length does not match the buffer in strict mode
+    #[error("Data length does not match buffer length")]
+    DataLengthMismatch,
+    // 11
+    /// Account must be writable
+    #[error("Account is not writable")]
+    AccountNotWritable,
+    // 12
+    /// Account is not the system program
+    #[error("Incorrect system program")]
+    IncorrectSystemProgram,
+    // 13
+    /// Destination account is not valid
+    #[error("Invalid destination")]
+    InvalidDestination,
+    // 14
+    /// Arithmetic overflow
+    #[error("Overflow")]
+    Overflow,
+    // 15
+    /// Buffer can not grow this much in a single instruction
+    #[error("Resize too large")]
+    ResizeTooLarge,
+    // 16
+    /// Signer is not the authority of the buffer
+    #[error("Invalid authority")]
+    InvalidAuthority,
+    // 17
+    /// Signer is not the pending authority of the buffer
+    #[error("Invalid pending authority")]
+    InvalidPendingAuthority,
+    // 18
+    /// Write does not fit in the buffer
+    #[error("Write out of bounds")]
+    WriteOutOfBounds,
+    // 19
+    /// Append does not fit in the remaining buffer capacity
+    #[error("Buffer full")]
+    BufferFull,
+    // 20
+    /// Operation is not supported by the buffer kind
+    #[error("Invalid buffer kind")]
+    InvalidBufferKind,
+    // 21
+    /// Message does not fit in the ring buffer
+    #[error("Message too large")]
+    MessageTooLarge,
+    // 22
+    /// Sequence number is not valid for the buffer
+    #[error("Invalid sequence number")]
+    InvalidSequence,
+    // 23
+    /// Account type tag does not match the expected account
+    #[error("Invalid account type")]
+    InvalidAccountType,
+    // 24
+    /// Account layout version is not supported, the account has to be migrated
+    #[error("Unsupported account version")]
+    UnsupportedVersion,
+    // 25
+    /// Account is already at the current layout version
+    #[error("Account already migrated")]
+    AlreadyMigrated,
+    // 26
+    /// Read does not fit in the buffer payload
+    #[error("Read out of bounds")]
+    ReadOutOfBounds,
+    // 27
+    /// Read is larger than the maximum return data size
+    #[error("Read too large")]
+    ReadTooLarge,
+    // 28
+    /// Return data was not set by the echo program
+    #[error("Invalid return data")]
+    InvalidReturnData,
+    // 29
+    /// Delegate record does not belong to the buffer and signer
+    #[error("Invalid delegate")]
+    InvalidDelegate,
+    // 30
+    /// Delegate can no longer write to the buffer
+    #[error("Delegate expired")]
+    DelegateExpired,
+    // 31
+    /// Delegate has used up its writes
+    #[error("Delegate quota exhausted")]
+    DelegateQuotaExhausted,
+    // 32
+    /// Multisig threshold or signer set is not valid
+    #[error("Invalid multisig")]
+    InvalidMultisig,
+    // 33
+    /// Fewer multisig signers than the threshold have signed
+    #[error("Not enough signers")]
+    NotEnoughSigners,
+    // 34
+    /// Write proposal does not match the buffer or data
+    #[error("Invalid write proposal")]
+    InvalidProposal,
+    // 35
+    /// Write proposal can no longer be approved or executed
+    #[error("Write proposal expired")]
+    ProposalExpired,
+    // 36
+    /// Signer already approved the write proposal
+    #[error("Write already approved")]
+    AlreadyApproved,
+    // 37
+    /// Buffer was written since the expected write sequence
+    #[error("Write sequence mismatch")]
+    WriteSequenceMismatch,
+    // 38
+    /// Buffer is frozen and can no longer be written, resized or closed
+    #[error("Buffer frozen")]
+    BufferFrozen,
+    // 39
+    /// Buffer still has delegate records that have to be revoked first
+    #[error("Buffer has delegates")]
+    BufferHasDelegates,
+}
+
+impl From<EchoError> for ProgramError {
+    fn from(e: EchoError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for EchoError {
+    fn type_of() -> &'static str {
+        "EchoError"
+    }
+}
+
+impl PrintProgramError for EchoError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        msg!(&self.to_string());
+    }
+}
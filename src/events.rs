@@ -0,0 +1,280 @@
+//! Borsh encoded events logged through `sol_log_data` on every state change
+//!
+//! Every event is logged as a single `Program data: <base64>` line, events
+//! can be decoded from the log messages of a transaction with [`decode_events`].
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{hash::hash, log::sol_log_data, pubkey::Pubkey};
+
+use crate::state::{AccountType, BufferKind};
+
+/// Prefix of the log lines written by `sol_log_data`
+pub const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+#[derive(Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+pub enum EchoEvent {
+    /// An authorized buffer was created by `authority`, which controls it alone
+    /// unless `threshold` is not 0, then `threshold` of `signers` control it
+    BufferCreated {
+        buffer: Pubkey,
+        authority: Pubkey,
+        buffer_seed: u64,
+        buffer_kind: BufferKind,
+        size: u64,
+        threshold: u8,
+        signers: Vec<Pubkey>,
+    },
+    /// `len` bytes were written at `offset` into the payload of a buffer,
+    /// `hash` is the sha256 of the written bytes. `signer` is the writer,
+    /// none for plain echo buffers anyone can write to
+    DataWritten {
+        buffer: Pubkey,
+        offset: u64,
+        len: u64,
+        hash: [u8; 32],
+        signer: Option<Pubkey>,
+    },
+    /// An authorized buffer was closed
+    BufferClosed {
+        buffer: Pubkey,
+        destination: Pubkey,
+        lamports: u64,
+    },
+    /// A vending machine buffer was created
+    VendingMachineInitialized {
+        buffer: Pubkey,
+        mint: Pubkey,
+        price: u64,
+        size: u64,
+    },
+    /// Tokens were burned to write to a vending machine buffer
+    TokensBurned {
+        buffer: Pubkey,
+        mint: Pubkey,
+        user: Pubkey,
+        amount: u64,
+    },
+    /// A plain echo buffer was created
+    EchoBufferCreated {
+        buffer: Pubkey,
+        payer: Pubkey,
+        size: u64,
+    },
+    /// A delegate was allowed to write to an authorized buffer
+    DelegateAdded {
+        buffer: Pubkey,
+        delegate: Pubkey,
+        expires_at_slot: u64,
+        max_writes: u64,
+    },
+    /// A delegate of an authorized buffer was revoked
+    DelegateRevoked { buffer: Pubkey, delegate: Pubkey },
+    /// A write to a multisig authorized buffer was proposed
+    WriteProposed {
+        buffer: Pubkey,
+        proposal: Pubkey,
+        proposer: Pubkey,
+        data_hash: [u8; 32],
+        expires_at_slot: u64,
+    },
+    /// A buffer signer approved a proposed write
+    WriteApproved {
+        buffer: Pubkey,
+        proposal: Pubkey,
+        signer: Pubkey,
+    },
+    /// An authorized buffer was frozen for good
+    BufferFrozen { buffer: Pubkey },
+    /// The authority of an authorized buffer proposed a new authority
+    AuthorityProposed {
+        buffer: Pubkey,
+        pending_authority: Pubkey,
+    },
+    /// The pending authority took over an authorized buffer alone
+    AuthorityChanged {
+        buffer: Pubkey,
+        authority: Pubkey,
+        authority_generation: u64,
+    },
+    /// An authorized buffer was resized to `new_size` bytes including its header
+    BufferResized {
+        buffer: Pubkey,
+        old_size: u64,
+        new_size: u64,
+    },
+    /// A consumer of a ring buffer was registered, `cursor` is the next
+    /// sequence number it has not consumed
+    ConsumerRegistered {
+        buffer: Pubkey,
+        consumer: Pubkey,
+        cursor: u64,
+    },
+    /// A consumer of a ring buffer consumed every message before `cursor`
+    Consumed {
+        buffer: Pubkey,
+        consumer: Pubkey,
+        cursor: u64,
+    },
+    /// A buffer was migrated from `old_version` to the current layout version,
+    /// 0 for buffers created before accounts were tagged
+    BufferMigrated {
+        buffer: Pubkey,
+        account_type: AccountType,
+        old_version: u8,
+        new_version: u8,
+        size: u64,
+    },
+    /// The cursor of a ring buffer consumer was closed
+    ConsumerClosed {
+        buffer: Pubkey,
+        consumer: Pubkey,
+        destination: Pubkey,
+        lamports: u64,
+    },
+}
+
+impl EchoEvent {
+    /// Event for `data` written by `writer` at `offset` into the payload of `buffer`
+    pub fn data_written(
+        buffer: &Pubkey,
+        writer: Option<&Pubkey>,
+        offset: usize,
+        data: &[u8],
+    ) -> Self {
+        Self::DataWritten {
+            buffer: *buffer,
+            offset: offset as u64,
+            len: data.len() as u64,
+            hash: hash(data).to_bytes(),
+            signer: writer.copied(),
+        }
+    }
+
+    /// Log the event
+    pub fn emit(&self) {
+        sol_log_data(&[&self.try_to_vec().unwrap()]);
+    }
+
+    /// Decode the event of a single `Program data: ` log line
+    #[cfg(not(target_os = "solana"))]
+    pub fn from_log(log: &str) -> Option<Self> {
+        let data = log.strip_prefix(PROGRAM_DATA_PREFIX)?;
+        let data = base64::decode(data.split_whitespace().next()?).ok()?;
+        Self::try_from_slice(&data).ok()
+    }
+}
+
+/// Decode the events logged by `program_id` from the log messages of a transaction
+///
+/// Tracks the invoke stack so data logged by other programs,
+/// including programs invoked by the echo program, is skipped.
+#[cfg(not(target_os = "solana"))]
+pub fn decode_events<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<EchoEvent> {
+    let program_id = program_id.to_string();
+    let mut invoke_stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for log in logs {
+        let log = log.as_ref();
+        let mut words = log.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("Program"), Some(program), Some("invoke")) => invoke_stack.push(program),
+            (Some("Program"), Some(_), Some("success"))
+            | (Some("Program"), Some(_), Some("failed:")) => {
+                invoke_stack.pop();
+            }
+            _ => {
+                if invoke_stack.last() == Some(&program_id.as_str()) {
+                    if let Some(event) = EchoEvent::from_log(log) {
+                        events.push(event);
+                    }
+                }
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program_data(event: &EchoEvent) -> String {
+        format!(
+            "{}{}",
+            PROGRAM_DATA_PREFIX,
+            base64::encode(event.try_to_vec().unwrap())
+        )
+    }
+
+    #[test]
+    fn test_decode_events() {
+        let program_id = Pubkey::new_unique();
+        let token_program = Pubkey::new_unique();
+        let written = EchoEvent::data_written(&Pubkey::new_unique(), None, 0, b"abc");
+        let burned = EchoEvent::TokensBurned {
+            buffer: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            amount: 3,
+        };
+        let other = EchoEvent::data_written(&Pubkey::new_unique(), None, 1, b"de");
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Vending machine echo".to_string(),
+            format!("Program {} invoke [2]", token_program),
+            program_data(&other),
+            format!("Program {} success", token_program),
+            program_data(&burned),
+            program_data(&written),
+            format!("Program {} success", program_id),
+            program_data(&other),
+        ];
+        assert_eq!(decode_events(&program_id, &logs), vec![burned, written]);
+    }
+
+    #[test]
+    fn test_from_log_round_trip() {
+        let buffer = Pubkey::new_unique();
+        let events = [
+            EchoEvent::BufferCreated {
+                buffer,
+                authority: Pubkey::new_unique(),
+                buffer_seed: 1,
+                buffer_kind: BufferKind::Ring,
+                size: 512,
+                threshold: 2,
+                signers: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            },
+            EchoEvent::AuthorityChanged {
+                buffer,
+                authority: Pubkey::new_unique(),
+                authority_generation: 1,
+            },
+            EchoEvent::BufferResized {
+                buffer,
+                old_size: 512,
+                new_size: 1024,
+            },
+            EchoEvent::Consumed {
+                buffer,
+                consumer: Pubkey::new_unique(),
+                cursor: 4,
+            },
+            EchoEvent::BufferMigrated {
+                buffer,
+                account_type: AccountType::AuthorizedBuffer,
+                old_version: 0,
+                new_version: AccountType::AuthorizedBuffer.current_version(),
+                size: 1024,
+            },
+        ];
+        for event in events {
+            assert_eq!(EchoEvent::from_log(&program_data(&event)), Some(event));
+        }
+    }
+
+    #[test]
+    fn test_from_log_rejects_other_lines() {
+        assert_eq!(EchoEvent::from_log("Program log: Echo account"), None);
+        assert_eq!(EchoEvent::from_log("Program data: !!!"), None);
+    }
+}
@@ -1,21 +1,47 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::state::{
+    find_authorized_buffer_address, find_consumer_cursor_address, find_delegate_record_address,
+    find_echo_buffer_address, find_vending_machine_address, find_write_proposal_address,
+    AccountType, BufferKind,
+};
+
+/// How `Echo` handles data that does not match the buffer length
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EchoMode {
+    /// write as much data as fits, leaving the rest of the buffer untouched
+    Truncate,
+    /// write as much data as fits, zeroing the rest of the buffer
+    ZeroPad,
+    /// data must be exactly as long as the buffer
+    Strict,
+}
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub enum EchoInstruction {
     /// Accounts:
     ///
-    /// echo_buffer: writable: true
+    /// echo_buffer: created by `InitializeEchoBuffer`
+    ///               writable: true
     ///               signer: false
-    Echo { data: Vec<u8> },
+    ///
+    /// Returns the number of data bytes written as a little endian `u64` through return data
+    Echo { data: Vec<u8>, mode: EchoMode },
     /// Initialize Authorized Echo
     ///
     /// input accounts:
     /// 1. authorized_buffer:
     ///     - signer: false
     ///     - writable: true
-    /// 2. authority:
+    /// 2. authority: pays for the buffer
     ///     - signer: true,
-    ///     - writable: false
+    ///     - writable: true
     /// 3. system_program
     ///     - signer: false
     ///     - writeable: false
@@ -23,15 +49,27 @@ pub enum EchoInstruction {
     InitializeAuthorizedEcho {
         buffer_seed: u64,
         buffer_size: usize,
+        buffer_kind: BufferKind,
     },
     /// Authorized echo
+    /// overwrites the buffer, appends to it if it is an append buffer,
+    /// or pushes a length prefixed message if it is a ring buffer,
+    /// evicting the oldest messages when the ring is full
     ///
     /// input accounts:
     /// 1. authorized_buffer:
     ///     -  signer: false
     ///     - writable: true
     ///
-    /// 2. authority:
+    /// 2. authority: authority of the buffer, a multisig signer, or a delegate
+    ///     - signer: true
+    ///     - writable: false
+    ///
+    /// 3. delegate_record: only when a delegate writes, uses up one of its writes
+    ///     - signer: false
+    ///     - writable: true
+    ///
+    /// 3.. co-signers: only for multisig buffers, other signers of the buffer
     ///     - signer: true
     ///     - writable: false
     ///
@@ -45,12 +83,1024 @@ pub enum EchoInstruction {
     ///     - writable: true
     /// 2. vending_machine_mint: token mint
     ///     - signer: false
-    ///     - writable: true
+    ///     - writable: false
     /// 3. payer:
     ///     - signer: true,
-    ///     - writable: false
+    ///     - writable: true
     /// 4. system_program
     ///     - signer: false
-    ///     - writable: fal
+    ///     - writable: false
     InitializeVendingMachine { price: u64, buffer_size: usize },
+    /// Vending machine echo
+    /// burns `price` tokens of the vending machine mint
+    /// and writes data after the buffer header
+    ///
+    /// input accounts:
+    /// 1. vending_machine_buffer: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. vending_machine_mint: token mint
+    ///     - signer: false
+    ///     - writable: true
+    /// 3. user:
+    ///     - signer: true
+    ///     - writable: false
+    /// 4. user_token_account: token account of vending_machine_mint owned by user
+    ///     - signer: false
+    ///     - writable: true
+    /// 5. token_program
+    ///     - signer: false
+    ///     - writable: false
+    VendingMachineEcho { data: Vec<u8> },
+    /// Close authorized buffer
+    /// zeroes the buffer and transfers all lamports to the destination,
+    /// all delegates have to be revoked first. Consumer cursors of
+    /// ring buffers are closed separately with `CloseConsumer`
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority:
+    ///     - signer: true
+    ///     - writable: false
+    /// 3. destination: receives the buffer lamports
+    ///     - signer: false
+    ///     - writable: true
+    CloseAuthorizedBuffer,
+    /// Resize authorized buffer
+    /// grows or shrinks the buffer in place, the authority
+    /// tops up or is refunded the rent difference
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority:
+    ///     - signer: true
+    ///     - writable: true
+    /// 3. system_program
+    ///     - signer: false
+    ///     - writable: false
+    ResizeAuthorizedBuffer { new_size: usize },
+    /// Propose a new authority for an authorized buffer
+    /// the new authority has to accept before it takes over
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority: current authority
+    ///     - signer: true
+    ///     - writable: false
+    ProposeAuthority { new_authority: Pubkey },
+    /// Accept the authority of an authorized buffer
+    /// delegates added by previous authorities can no longer write
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. new_authority: pending authority
+    ///     - signer: true
+    ///     - writable: false
+    AcceptAuthority,
+    /// Echo at offset
+    /// writes data to the echo buffer payload starting at `offset`
+    /// leaving the rest of the buffer untouched
+    ///
+    /// input accounts:
+    /// 1. echo_buffer: created by `InitializeEchoBuffer`
+    ///     - signer: false
+    ///     - writable: true
+    EchoAt { offset: usize, data: Vec<u8> },
+    /// Authorized echo at offset
+    /// writes data to the authorized buffer starting at `offset`
+    /// past the header, leaving the rest of the buffer untouched.
+    /// Only supported by overwrite buffers, ring and append buffers fail
+    /// with `InvalidBufferKind` (error code 20).
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer: buffer of kind overwrite
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority: authority of the buffer or a multisig signer
+    ///     - signer: true
+    ///     - writable: false
+    ///
+    /// 3.. co-signers: only for multisig buffers, other signers of the buffer
+    ///     - signer: true
+    ///     - writable: false
+    AuthorizedEchoAt { offset: usize, data: Vec<u8> },
+    /// Register a consumer of a ring buffer
+    /// creates the consumer cursor at the oldest stored message
+    ///
+    /// input accounts:
+    /// 1. ring_buffer: authorized buffer of kind ring
+    ///     - signer: false
+    ///     - writable: false
+    /// 2. authority: pays for the consumer cursor
+    ///     - signer: true
+    ///     - writable: true
+    /// 3. consumer_cursor: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 4. system_program
+    ///     - signer: false
+    ///     - writable: false
+    RegisterConsumer { consumer: Pubkey },
+    /// Consume messages of a ring buffer
+    /// advances the consumer cursor past `up_to_seq`
+    ///
+    /// input accounts:
+    /// 1. ring_buffer: authorized buffer of kind ring
+    ///     - signer: false
+    ///     - writable: false
+    /// 2. consumer_cursor: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 3. consumer:
+    ///     - signer: true
+    ///     - writable: false
+    Consume { up_to_seq: u64 },
+    /// Upgrade a buffer created before accounts were tagged, or an authorized
+    /// buffer of an older layout version, to the current layout version,
+    /// preserving its payload
+    ///
+    /// v0 echo buffers have no header, the whole account is kept as payload.
+    /// Their payload must not start with a valid account tag and version,
+    /// those are taken for tagged accounts
+    ///
+    /// input accounts:
+    /// 1. buffer: v0 or older version pda of echo program, or v0 echo buffer
+    ///     - signer: true for v0 echo buffers, which are keypair accounts, false otherwise
+    ///     - writable: true
+    /// 2. payer: pays for the grown header, for v0 authorized buffers
+    ///    the authority the buffer is derived from
+    ///     - signer: true
+    ///     - writable: true
+    /// 3. system_program
+    ///     - signer: false
+    ///     - writable: false
+    /// 4. vending_machine_mint: only for vending machine buffers
+    ///     - signer: false
+    ///     - writable: false
+    MigrateBuffer { account_type: AccountType },
+    /// Return `len` bytes of the buffer payload starting at `offset`
+    /// through return data, the header is not part of the payload
+    ///
+    /// input accounts:
+    /// 1. buffer: tagged echo, authorized or vending machine buffer
+    ///     - signer: false
+    ///     - writable: false
+    ReadBuffer { offset: usize, len: usize },
+    /// Create a plain echo buffer of `size` bytes including its header
+    ///
+    /// The buffer is a keypair account when `buffer_seed` is `None`,
+    /// otherwise the pda derived from the payer and `buffer_seed`
+    ///
+    /// input accounts:
+    /// 1. echo_buffer: new account or pda of echo program
+    ///     - signer: true for keypair accounts
+    ///     - writable: true
+    /// 2. payer: pays for the echo buffer
+    ///     - signer: true
+    ///     - writable: true
+    /// 3. system_program
+    ///     - signer: false
+    ///     - writable: false
+    InitializeEchoBuffer {
+        size: usize,
+        buffer_seed: Option<u64>,
+    },
+    /// Allow `delegate` to `AuthorizedEcho` up to `max_writes` times
+    /// until `expires_at_slot`, replacing any previous allowance.
+    /// The delegate can write until the authority changes
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority: current authority, pays for the delegate record
+    ///     - signer: true
+    ///     - writable: true
+    /// 3. delegate_record: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 4. system_program
+    ///     - signer: false
+    ///     - writable: false
+    AddDelegate {
+        delegate: Pubkey,
+        expires_at_slot: u64,
+        max_writes: u64,
+    },
+    /// Revoke a delegate, closing its record
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority: current authority, receives the record lamports
+    ///     - signer: true
+    ///     - writable: true
+    /// 3. delegate_record: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    RevokeDelegate,
+    /// Initialize an authorized buffer controlled by `threshold` of `signers`
+    ///
+    /// Instructions that require the buffer authority take the other signers
+    /// as additional signer accounts after their own accounts, and succeed
+    /// once `threshold` of `signers` have signed. An accepted
+    /// `ProposeAuthority` hands the buffer over to a single authority.
+    ///
+    /// The header grows by the space of the signers, `buffer_size` has to
+    /// exceed `multisig_auth_buffer_header_size(signers.len())`
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer: pda derived from the payer and `buffer_seed`
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. payer: pays for the buffer
+    ///     - signer: true,
+    ///     - writable: true
+    /// 3. system_program
+    ///     - signer: false
+    ///     - writeable: false
+    InitializeMultisigEcho {
+        buffer_seed: u64,
+        buffer_size: usize,
+        buffer_kind: BufferKind,
+        threshold: u8,
+        signers: Vec<Pubkey>,
+    },
+    /// Propose writing `data` to a multisig buffer, approved by the proposer
+    ///
+    /// The write is applied like `AuthorizedEcho` by `ExecuteWrite` once
+    /// `threshold` buffer signers approved it, until `expires_in_slots`
+    /// slots from now.
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer: multisig buffer
+    ///     - signer: false
+    ///     - writable: false
+    /// 2. proposer: buffer signer, pays for the proposal
+    ///     - signer: true
+    ///     - writable: true
+    /// 3. write_proposal: pda of echo program derived from the buffer and `data_hash`
+    ///     - signer: false
+    ///     - writable: true
+    /// 4. system_program
+    ///     - signer: false
+    ///     - writable: false
+    ProposeWrite {
+        data_hash: [u8; 32],
+        data: Vec<u8>,
+        expires_in_slots: u64,
+    },
+    /// Approve a proposed write
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: false
+    /// 2. write_proposal: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 3. signer: buffer signer
+    ///     - signer: true
+    ///     - writable: false
+    ApproveWrite,
+    /// Apply an approved write to the buffer and close the proposal,
+    /// anyone can execute a write once it has enough approvals
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. write_proposal: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 3. proposer: receives the proposal lamports
+    ///     - signer: false
+    ///     - writable: true
+    ExecuteWrite,
+    /// Close a write proposal without applying it,
+    /// by the proposer or by anyone once it expired
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: false
+    /// 2. write_proposal: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 3. proposer: receives the proposal lamports
+    ///     - signer: true unless the proposal expired
+    ///     - writable: true
+    CancelWrite,
+    /// `AuthorizedEcho` that only writes if the buffer write sequence
+    /// is still `expected_seq`, accounts are the same
+    ///
+    /// Returns the new write sequence as a little endian `u64` through return data
+    AuthorizedEchoCas { data: Vec<u8>, expected_seq: u64 },
+    /// Freeze an authorized buffer for good, every later write,
+    /// offset write, resize and close of the buffer fails
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority:
+    ///     - signer: true
+    ///     - writable: false
+    FreezeAuthorizedBuffer,
+    /// Initialize an overwrite authorized buffer holding `data`
+    /// at the start of its payload, frozen from the start
+    ///
+    /// input accounts:
+    /// 1. authorized_buffer:
+    ///     - signer: false
+    ///     - writable: true
+    /// 2. authority: pays for the buffer
+    ///     - signer: true,
+    ///     - writable: true
+    /// 3. system_program
+    ///     - signer: false
+    ///     - writeable: false
+    InitializeImmutableEcho {
+        buffer_seed: u64,
+        buffer_size: usize,
+        data: Vec<u8>,
+    },
+    /// Close a consumer cursor of a ring buffer
+    /// zeroes the cursor and transfers its lamports to the destination,
+    /// the ring buffer may already be closed if the consumer closes it
+    ///
+    /// input accounts:
+    /// 1. ring_buffer: authorized buffer the cursor belongs to
+    ///     - signer: false
+    ///     - writable: false
+    /// 2. consumer_cursor: pda of echo program
+    ///     - signer: false
+    ///     - writable: true
+    /// 3. authority: the consumer, or the authority of the ring buffer
+    ///     - signer: true
+    ///     - writable: false
+    /// 4. destination: receives the cursor lamports
+    ///     - signer: false
+    ///     - writable: true
+    ///
+    /// 5.. co-signers: only for multisig buffers closed by the buffer signers
+    ///     - signer: true
+    ///     - writable: false
+    CloseConsumer,
+}
+
+impl EchoInstruction {
+    /// Serialize the instruction into instruction data
+    pub fn pack(&self) -> Vec<u8> {
+        self.try_to_vec().unwrap()
+    }
+}
+
+/// Creates an `Echo` instruction
+pub fn echo(
+    program_id: &Pubkey,
+    echo_buffer: &Pubkey,
+    data: Vec<u8>,
+    mode: EchoMode,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new(*echo_buffer, false)],
+        data: EchoInstruction::Echo { data, mode }.pack(),
+    }
+}
+
+/// Creates an `InitializeAuthorizedEcho` instruction for the buffer derived from
+/// `authority` and `buffer_seed`
+pub fn initialize_authorized_echo(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    buffer_seed: u64,
+    buffer_size: usize,
+    buffer_kind: BufferKind,
+) -> Instruction {
+    let (authorized_buffer, _) = find_authorized_buffer_address(program_id, authority, buffer_seed);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(authorized_buffer, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::InitializeAuthorizedEcho {
+            buffer_seed,
+            buffer_size,
+            buffer_kind,
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `AuthorizedEcho` instruction
+pub fn authorized_echo(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: EchoInstruction::AuthorizedEcho { data }.pack(),
+    }
+}
+
+/// Creates an `AuthorizedEcho` instruction signed by `delegate` instead of the authority
+pub fn delegated_authorized_echo(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    delegate: &Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    let (delegate_record, _) =
+        find_delegate_record_address(program_id, authorized_buffer, delegate);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*delegate, true),
+            AccountMeta::new(delegate_record, false),
+        ],
+        data: EchoInstruction::AuthorizedEcho { data }.pack(),
+    }
+}
+
+/// Creates an `InitializeVendingMachine` instruction for the buffer derived from
+/// `vending_machine_mint` and `price`
+pub fn initialize_vending_machine(
+    program_id: &Pubkey,
+    vending_machine_mint: &Pubkey,
+    payer: &Pubkey,
+    price: u64,
+    buffer_size: usize,
+) -> Instruction {
+    let (vending_machine_buffer, _) =
+        find_vending_machine_address(program_id, vending_machine_mint, price);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(vending_machine_buffer, false),
+            AccountMeta::new_readonly(*vending_machine_mint, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::InitializeVendingMachine { price, buffer_size }.pack(),
+    }
+}
+
+/// Creates a `VendingMachineEcho` instruction
+pub fn vending_machine_echo(
+    program_id: &Pubkey,
+    vending_machine_buffer: &Pubkey,
+    vending_machine_mint: &Pubkey,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*vending_machine_buffer, false),
+            AccountMeta::new(*vending_machine_mint, false),
+            AccountMeta::new_readonly(*user, true),
+            AccountMeta::new(*user_token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EchoInstruction::VendingMachineEcho { data }.pack(),
+    }
+}
+
+/// Creates a `CloseAuthorizedBuffer` instruction
+pub fn close_authorized_buffer(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*destination, false),
+        ],
+        data: EchoInstruction::CloseAuthorizedBuffer.pack(),
+    }
+}
+
+/// Creates a `ResizeAuthorizedBuffer` instruction
+pub fn resize_authorized_buffer(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    new_size: usize,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::ResizeAuthorizedBuffer { new_size }.pack(),
+    }
+}
+
+/// Creates a `ProposeAuthority` instruction
+pub fn propose_authority(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: EchoInstruction::ProposeAuthority {
+            new_authority: *new_authority,
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `AcceptAuthority` instruction
+pub fn accept_authority(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*new_authority, true),
+        ],
+        data: EchoInstruction::AcceptAuthority.pack(),
+    }
+}
+
+/// Creates an `EchoAt` instruction
+pub fn echo_at(
+    program_id: &Pubkey,
+    echo_buffer: &Pubkey,
+    offset: usize,
+    data: Vec<u8>,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new(*echo_buffer, false)],
+        data: EchoInstruction::EchoAt { offset, data }.pack(),
+    }
+}
+
+/// Creates an `AuthorizedEchoAt` instruction
+pub fn authorized_echo_at(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    offset: usize,
+    data: Vec<u8>,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: EchoInstruction::AuthorizedEchoAt { offset, data }.pack(),
+    }
+}
+
+/// Creates a `RegisterConsumer` instruction
+pub fn register_consumer(
+    program_id: &Pubkey,
+    ring_buffer: &Pubkey,
+    authority: &Pubkey,
+    consumer: &Pubkey,
+) -> Instruction {
+    let (consumer_cursor, _) = find_consumer_cursor_address(program_id, ring_buffer, consumer);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*ring_buffer, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(consumer_cursor, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::RegisterConsumer {
+            consumer: *consumer,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `Consume` instruction
+pub fn consume(
+    program_id: &Pubkey,
+    ring_buffer: &Pubkey,
+    consumer: &Pubkey,
+    up_to_seq: u64,
+) -> Instruction {
+    let (consumer_cursor, _) = find_consumer_cursor_address(program_id, ring_buffer, consumer);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*ring_buffer, false),
+            AccountMeta::new(consumer_cursor, false),
+            AccountMeta::new_readonly(*consumer, true),
+        ],
+        data: EchoInstruction::Consume { up_to_seq }.pack(),
+    }
+}
+
+/// Creates a `CloseConsumer` instruction for the cursor of `consumer`,
+/// `authority` is either the consumer or the authority of the ring buffer
+pub fn close_consumer(
+    program_id: &Pubkey,
+    ring_buffer: &Pubkey,
+    authority: &Pubkey,
+    consumer: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    let (consumer_cursor, _) = find_consumer_cursor_address(program_id, ring_buffer, consumer);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*ring_buffer, false),
+            AccountMeta::new(consumer_cursor, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*destination, false),
+        ],
+        data: EchoInstruction::CloseConsumer.pack(),
+    }
+}
+
+/// Creates a `MigrateBuffer` instruction for a v0 or older version authorized buffer
+pub fn migrate_authorized_buffer(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::MigrateBuffer {
+            account_type: AccountType::AuthorizedBuffer,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `MigrateBuffer` instruction for a v0 vending machine buffer
+pub fn migrate_vending_machine_buffer(
+    program_id: &Pubkey,
+    vending_machine_buffer: &Pubkey,
+    vending_machine_mint: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*vending_machine_buffer, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*vending_machine_mint, false),
+        ],
+        data: EchoInstruction::MigrateBuffer {
+            account_type: AccountType::VendingMachineBuffer,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `MigrateBuffer` instruction for the v0 echo buffer keypair account `echo_buffer`
+pub fn migrate_echo_buffer(
+    program_id: &Pubkey,
+    echo_buffer: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*echo_buffer, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::MigrateBuffer {
+            account_type: AccountType::EchoBuffer,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `ReadBuffer` instruction
+pub fn read_buffer(program_id: &Pubkey, buffer: &Pubkey, offset: usize, len: usize) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new_readonly(*buffer, false)],
+        data: EchoInstruction::ReadBuffer { offset, len }.pack(),
+    }
+}
+
+/// Creates an `InitializeEchoBuffer` instruction for the keypair account `echo_buffer`
+pub fn initialize_echo_buffer(
+    program_id: &Pubkey,
+    echo_buffer: &Pubkey,
+    payer: &Pubkey,
+    size: usize,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*echo_buffer, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::InitializeEchoBuffer {
+            size,
+            buffer_seed: None,
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `InitializeEchoBuffer` instruction for the buffer derived from
+/// `payer` and `buffer_seed`
+pub fn initialize_echo_buffer_pda(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    buffer_seed: u64,
+    size: usize,
+) -> Instruction {
+    let (echo_buffer, _) = find_echo_buffer_address(program_id, payer, buffer_seed);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(echo_buffer, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::InitializeEchoBuffer {
+            size,
+            buffer_seed: Some(buffer_seed),
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `AddDelegate` instruction
+pub fn add_delegate(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    delegate: &Pubkey,
+    expires_at_slot: u64,
+    max_writes: u64,
+) -> Instruction {
+    let (delegate_record, _) =
+        find_delegate_record_address(program_id, authorized_buffer, delegate);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(delegate_record, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::AddDelegate {
+            delegate: *delegate,
+            expires_at_slot,
+            max_writes,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `RevokeDelegate` instruction
+pub fn revoke_delegate(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    delegate: &Pubkey,
+) -> Instruction {
+    let (delegate_record, _) =
+        find_delegate_record_address(program_id, authorized_buffer, delegate);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(delegate_record, false),
+        ],
+        data: EchoInstruction::RevokeDelegate.pack(),
+    }
+}
+
+/// Creates an `InitializeMultisigEcho` instruction for the buffer derived from
+/// `payer` and `buffer_seed`
+pub fn initialize_multisig_echo(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    buffer_seed: u64,
+    buffer_size: usize,
+    buffer_kind: BufferKind,
+    threshold: u8,
+    signers: Vec<Pubkey>,
+) -> Instruction {
+    let (authorized_buffer, _) = find_authorized_buffer_address(program_id, payer, buffer_seed);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(authorized_buffer, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::InitializeMultisigEcho {
+            buffer_seed,
+            buffer_size,
+            buffer_kind,
+            threshold,
+            signers,
+        }
+        .pack(),
+    }
+}
+
+/// Adds `co_signers` of a multisig buffer as signer accounts to `instruction`
+pub fn with_co_signers(mut instruction: Instruction, co_signers: &[Pubkey]) -> Instruction {
+    instruction.accounts.extend(
+        co_signers
+            .iter()
+            .map(|co_signer| AccountMeta::new_readonly(*co_signer, true)),
+    );
+    instruction
+}
+
+/// Creates a `ProposeWrite` instruction
+pub fn propose_write(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    proposer: &Pubkey,
+    data: Vec<u8>,
+    expires_in_slots: u64,
+) -> Instruction {
+    let data_hash = hash(&data).to_bytes();
+    let (write_proposal, _) =
+        find_write_proposal_address(program_id, authorized_buffer, &data_hash);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authorized_buffer, false),
+            AccountMeta::new(*proposer, true),
+            AccountMeta::new(write_proposal, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::ProposeWrite {
+            data_hash,
+            data,
+            expires_in_slots,
+        }
+        .pack(),
+    }
+}
+
+/// Creates an `ApproveWrite` instruction for the proposed data with `data_hash`
+pub fn approve_write(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    signer: &Pubkey,
+    data_hash: &[u8; 32],
+) -> Instruction {
+    let (write_proposal, _) = find_write_proposal_address(program_id, authorized_buffer, data_hash);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authorized_buffer, false),
+            AccountMeta::new(write_proposal, false),
+            AccountMeta::new_readonly(*signer, true),
+        ],
+        data: EchoInstruction::ApproveWrite.pack(),
+    }
+}
+
+/// Creates an `ExecuteWrite` instruction for the proposed data with `data_hash`
+pub fn execute_write(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    proposer: &Pubkey,
+    data_hash: &[u8; 32],
+) -> Instruction {
+    let (write_proposal, _) = find_write_proposal_address(program_id, authorized_buffer, data_hash);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new(write_proposal, false),
+            AccountMeta::new(*proposer, false),
+        ],
+        data: EchoInstruction::ExecuteWrite.pack(),
+    }
+}
+
+/// Creates a `CancelWrite` instruction signed by the proposer
+pub fn cancel_write(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    proposer: &Pubkey,
+    data_hash: &[u8; 32],
+) -> Instruction {
+    let (write_proposal, _) = find_write_proposal_address(program_id, authorized_buffer, data_hash);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authorized_buffer, false),
+            AccountMeta::new(write_proposal, false),
+            AccountMeta::new(*proposer, true),
+        ],
+        data: EchoInstruction::CancelWrite.pack(),
+    }
+}
+
+/// Creates an `AuthorizedEchoCas` instruction
+pub fn authorized_echo_cas(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+    data: Vec<u8>,
+    expected_seq: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: EchoInstruction::AuthorizedEchoCas { data, expected_seq }.pack(),
+    }
+}
+
+/// Creates a `FreezeAuthorizedBuffer` instruction
+pub fn freeze_authorized_buffer(
+    program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: EchoInstruction::FreezeAuthorizedBuffer.pack(),
+    }
+}
+
+/// Creates an `InitializeImmutableEcho` instruction for the buffer derived from
+/// `authority` and `buffer_seed`
+pub fn initialize_immutable_echo(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    buffer_seed: u64,
+    buffer_size: usize,
+    data: Vec<u8>,
+) -> Instruction {
+    let (authorized_buffer, _) = find_authorized_buffer_address(program_id, authority, buffer_seed);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(authorized_buffer, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EchoInstruction::InitializeImmutableEcho {
+            buffer_seed,
+            buffer_size,
+            data,
+        }
+        .pack(),
+    }
 }
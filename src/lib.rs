@@ -1,4 +1,10 @@
+pub mod cpi;
+#[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
+pub mod error;
+pub mod events;
 pub mod instruction;
 pub mod processor;
+pub mod ring;
 pub mod state;
+pub mod validation;
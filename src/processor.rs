@@ -1,332 +1,1818 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshDeserialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint::ProgramResult,
+    clock::Clock,
+    entrypoint::{ProgramResult, MAX_PERMITTED_DATA_INCREASE},
+    hash::hash,
     msg,
-    program::invoke_signed,
-    program_error::ProgramError,
+    program::{invoke, invoke_signed, set_return_data, MAX_RETURN_DATA},
     program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
     system_instruction,
     sysvar::Sysvar,
 };
-use spl_token::state::Mint;
+use spl_token::state::{Account as TokenAccount, Mint};
 
-use crate::instruction::EchoInstruction;
-use crate::state::{AuthorizedBufferHeader, VendingMachineBufferHeader, AUTH_BUFFER_HEADER_SIZE};
+use crate::error::EchoError;
+use crate::events::EchoEvent;
+use crate::instruction::{EchoInstruction, EchoMode};
+use crate::ring::push_message;
+use crate::state::{
+    multisig_auth_buffer_header_size, AccountType, AuthorizedBufferHeader,
+    AuthorizedBufferHeaderV0, BufferKind, ConsumerCursor, DelegateRecord, EchoBufferHeader,
+    VendingMachineBufferHeader, VendingMachineBufferHeaderV0, WriteProposal,
+    AUTHORIZED_BUFFER_SEED, AUTH_BUFFER_HEADER_SIZE, CONSUMER_CURSOR_SEED, CONSUMER_CURSOR_SIZE,
+    DELEGATE_RECORD_SEED, DELEGATE_RECORD_SIZE, ECHO_BUFFER_HEADER_SIZE, ECHO_BUFFER_SEED,
+    V0_HEADER_SIZE, VENDING_MACHINE_BUFFER_HEADER_SIZE, VENDING_MACHINE_SEED,
+    WRITE_PROPOSAL_HEADER_SIZE, WRITE_PROPOSAL_SEED,
+};
+use crate::validation::{
+    assert_authorized_buffer, assert_authorized_buffer_pda, assert_authorized_writer,
+    assert_buffer_authority, assert_derivation, assert_echo_buffer, assert_multisig,
+    assert_not_frozen, assert_owned_by, assert_program_address, assert_signer,
+    assert_system_program, assert_token_program, assert_writable, assert_write_proposal,
+};
 pub struct Processor {}
 
 impl Processor {
     pub fn process_instruction(
-        _program_id: &Pubkey,
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         instruction_data: &[u8],
     ) -> ProgramResult {
         let instruction = EchoInstruction::try_from_slice(instruction_data)
-            .map_err(|_| ProgramError::InvalidInstructionData)?;
+            .map_err(|_| EchoError::InvalidInstruction)?;
 
         match instruction {
-            EchoInstruction::Echo { data } => {
+            EchoInstruction::Echo { data, mode } => {
                 msg!("Echo account");
-                let account_iter = &mut accounts.iter();
-                let echo_buffer = next_account_info(account_iter)?;
-                let buffer = &mut (*echo_buffer.data).borrow_mut();
-                msg!("data_: {:?} ", data);
-                if buffer.len() == 0 {
-                    msg!("Account has data length of 0. Failing. ");
-                    return Err(ProgramError::AccountDataTooSmall);
-                }
-                let bytes_to_copy = buffer.len();
-                for index in 0..bytes_to_copy {
-                    buffer[index] = data[index]
-                }
-                msg!(
-                    "Successfully wrote {} bytes to account of size {}",
-                    bytes_to_copy,
-                    buffer.len()
-                );
+                Self::process_echo(program_id, accounts, data, mode)
             }
             EchoInstruction::InitializeAuthorizedEcho {
                 buffer_seed,
                 buffer_size,
+                buffer_kind,
             } => {
                 msg!("Initialize Authorized echo");
-                if buffer_size <= AUTH_BUFFER_HEADER_SIZE {
-                    msg!(
-                        "Invalid buffer length {}, must be greater than header size {}",
-                        buffer_size,
-                        AUTH_BUFFER_HEADER_SIZE
-                    );
-                    return Err(ProgramError::InvalidArgument);
-                }
-                let account_iter = &mut accounts.iter();
-                let authorized_buffer = next_account_info(account_iter)?;
-                let authority = next_account_info(account_iter)?;
-                let system_program = next_account_info(account_iter)?;
+                Self::process_initialize_authorized_echo(
+                    program_id,
+                    accounts,
+                    buffer_seed,
+                    buffer_size,
+                    buffer_kind,
+                    None,
+                )
+            }
+            EchoInstruction::AuthorizedEcho { data } => {
+                msg!("Authorized echo");
+                Self::process_authorized_echo(program_id, accounts, data)
+            }
+            EchoInstruction::InitializeVendingMachine { price, buffer_size } => {
+                msg!("Initialize vending machine");
+                Self::process_initialize_vending_machine(program_id, accounts, price, buffer_size)
+            }
+            EchoInstruction::VendingMachineEcho { data } => {
+                msg!("Vending machine echo");
+                Self::process_vending_machine_echo(program_id, accounts, data)
+            }
+            EchoInstruction::CloseAuthorizedBuffer => {
+                msg!("Close authorized buffer");
+                Self::process_close_authorized_buffer(program_id, accounts)
+            }
+            EchoInstruction::ResizeAuthorizedBuffer { new_size } => {
+                msg!("Resize authorized buffer");
+                Self::process_resize_authorized_buffer(program_id, accounts, new_size)
+            }
+            EchoInstruction::ProposeAuthority { new_authority } => {
+                msg!("Propose authority");
+                Self::process_propose_authority(program_id, accounts, new_authority)
+            }
+            EchoInstruction::AcceptAuthority => {
+                msg!("Accept authority");
+                Self::process_accept_authority(program_id, accounts)
+            }
+            EchoInstruction::EchoAt { offset, data } => {
+                msg!("Echo at offset");
+                Self::process_echo_at(program_id, accounts, offset, data)
+            }
+            EchoInstruction::AuthorizedEchoAt { offset, data } => {
+                msg!("Authorized echo at offset");
+                Self::process_authorized_echo_at(program_id, accounts, offset, data)
+            }
+            EchoInstruction::RegisterConsumer { consumer } => {
+                msg!("Register consumer");
+                Self::process_register_consumer(program_id, accounts, consumer)
+            }
+            EchoInstruction::Consume { up_to_seq } => {
+                msg!("Consume");
+                Self::process_consume(program_id, accounts, up_to_seq)
+            }
+            EchoInstruction::MigrateBuffer { account_type } => {
+                msg!("Migrate buffer");
+                Self::process_migrate_buffer(program_id, accounts, account_type)
+            }
+            EchoInstruction::ReadBuffer { offset, len } => {
+                msg!("Read buffer");
+                Self::process_read_buffer(program_id, accounts, offset, len)
+            }
+            EchoInstruction::InitializeEchoBuffer { size, buffer_seed } => {
+                msg!("Initialize echo buffer");
+                Self::process_initialize_echo_buffer(program_id, accounts, size, buffer_seed)
+            }
+            EchoInstruction::AddDelegate {
+                delegate,
+                expires_at_slot,
+                max_writes,
+            } => {
+                msg!("Add delegate");
+                Self::process_add_delegate(
+                    program_id,
+                    accounts,
+                    delegate,
+                    expires_at_slot,
+                    max_writes,
+                )
+            }
+            EchoInstruction::RevokeDelegate => {
+                msg!("Revoke delegate");
+                Self::process_revoke_delegate(program_id, accounts)
+            }
+            EchoInstruction::InitializeMultisigEcho {
+                buffer_seed,
+                buffer_size,
+                buffer_kind,
+                threshold,
+                signers,
+            } => {
+                msg!("Initialize multisig echo");
+                Self::process_initialize_authorized_echo(
+                    program_id,
+                    accounts,
+                    buffer_seed,
+                    buffer_size,
+                    buffer_kind,
+                    Some((threshold, signers)),
+                )
+            }
+            EchoInstruction::ProposeWrite {
+                data_hash,
+                data,
+                expires_in_slots,
+            } => {
+                msg!("Propose write");
+                Self::process_propose_write(program_id, accounts, data_hash, data, expires_in_slots)
+            }
+            EchoInstruction::ApproveWrite => {
+                msg!("Approve write");
+                Self::process_approve_write(program_id, accounts)
+            }
+            EchoInstruction::ExecuteWrite => {
+                msg!("Execute write");
+                Self::process_execute_write(program_id, accounts)
+            }
+            EchoInstruction::CancelWrite => {
+                msg!("Cancel write");
+                Self::process_cancel_write(program_id, accounts)
+            }
+            EchoInstruction::AuthorizedEchoCas { data, expected_seq } => {
+                msg!("Authorized echo compare and swap");
+                Self::process_authorized_echo_cas(program_id, accounts, data, expected_seq)
+            }
+            EchoInstruction::FreezeAuthorizedBuffer => {
+                msg!("Freeze authorized buffer");
+                Self::process_freeze_authorized_buffer(program_id, accounts)
+            }
+            EchoInstruction::InitializeImmutableEcho {
+                buffer_seed,
+                buffer_size,
+                data,
+            } => {
+                msg!("Initialize immutable echo");
+                Self::process_initialize_immutable_echo(
+                    program_id,
+                    accounts,
+                    buffer_seed,
+                    buffer_size,
+                    data,
+                )
+            }
+            EchoInstruction::CloseConsumer => {
+                msg!("Close consumer");
+                Self::process_close_consumer(program_id, accounts)
+            }
+        }
+    }
 
-                let buffer_seed_b = buffer_seed.to_le_bytes();
+    fn process_echo(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        data: Vec<u8>,
+        mode: EchoMode,
+    ) -> ProgramResult {
+        let account_iter = &mut accounts.iter();
+        let echo_buffer = next_account_info(account_iter)?;
+        assert_echo_buffer(program_id, echo_buffer)?;
 
-                let (pubkey, bump_seed) = Pubkey::find_program_address(
-                    &[b"authority", authority.key.as_ref(), &buffer_seed_b],
-                    &_program_id,
-                );
+        let buffer = &mut echo_buffer.try_borrow_mut_data()?;
+        let buffer = &mut buffer[ECHO_BUFFER_HEADER_SIZE..];
+        if mode == EchoMode::Strict && data.len() != buffer.len() {
+            msg!(
+                "Data length {} does not match buffer length {}",
+                data.len(),
+                buffer.len()
+            );
+            return Err(EchoError::DataLengthMismatch.into());
+        }
+        let bytes_to_copy = data.len().min(buffer.len());
+        buffer[..bytes_to_copy].copy_from_slice(&data[..bytes_to_copy]);
+        if mode == EchoMode::ZeroPad {
+            buffer[bytes_to_copy..].fill(0);
+        }
+        EchoEvent::data_written(echo_buffer.key, None, 0, &data[..bytes_to_copy]).emit();
+        set_return_data(&(bytes_to_copy as u64).to_le_bytes());
+        msg!(
+            "Successfully wrote {} bytes to account of size {}",
+            bytes_to_copy,
+            buffer.len()
+        );
+        Ok(())
+    }
 
-                if pubkey != *authorized_buffer.key {
-                    msg!("authorized buffer is not a correct PDA");
-                    return Err(ProgramError::InvalidAccountData);
-                }
+    fn process_initialize_authorized_echo(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        buffer_seed: u64,
+        buffer_size: usize,
+        buffer_kind: BufferKind,
+        multisig: Option<(u8, Vec<Pubkey>)>,
+    ) -> ProgramResult {
+        let header_size = match &multisig {
+            Some((threshold, signers)) => {
+                assert_multisig(*threshold, signers)?;
+                multisig_auth_buffer_header_size(signers.len())
+            }
+            None => AUTH_BUFFER_HEADER_SIZE,
+        };
+        if buffer_size <= header_size {
+            msg!(
+                "Invalid buffer length {}, must be greater than header size {}",
+                buffer_size,
+                header_size
+            );
+            return Err(EchoError::BufferTooSmall.into());
+        }
+        let account_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(account_iter)?;
+        let authority = next_account_info(account_iter)?;
+        let system_program = next_account_info(account_iter)?;
 
-                // create pda
-                let create_account_ix = system_instruction::create_account(
-                    &authority.key,
-                    &authorized_buffer.key,
-                    Rent::get()?.minimum_balance(buffer_size),
-                    buffer_size as u64,
-                    _program_id,
-                );
+        assert_writable(authorized_buffer)?;
+        assert_signer(authority)?;
+        assert_writable(authority)?;
+        assert_system_program(system_program)?;
 
-                invoke_signed(
-                    &create_account_ix,
-                    &[
-                        authorized_buffer.clone(),
-                        authority.clone(),
-                        system_program.clone(),
-                    ],
-                    &[&[
-                        b"authority",
-                        authority.key.as_ref(),
-                        &buffer_seed.to_le_bytes(),
-                        &[bump_seed],
-                    ]],
-                )?;
-                let buffer = &mut (*authorized_buffer.data).borrow_mut();
-                let buffer_header = AuthorizedBufferHeader {
+        let buffer_seed_b = buffer_seed.to_le_bytes();
+        let bump_seed = assert_derivation(
+            program_id,
+            authorized_buffer,
+            &[
+                AUTHORIZED_BUFFER_SEED,
+                authority.key.as_ref(),
+                &buffer_seed_b,
+            ],
+        )?;
+
+        // create pda
+        let create_account_ix = system_instruction::create_account(
+            authority.key,
+            authorized_buffer.key,
+            Rent::get()?.minimum_balance(buffer_size),
+            buffer_size as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_account_ix,
+            &[
+                authorized_buffer.clone(),
+                authority.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                AUTHORIZED_BUFFER_SEED,
+                authority.key.as_ref(),
+                &buffer_seed_b,
+                &[bump_seed],
+            ]],
+        )?;
+        let buffer = &mut authorized_buffer.try_borrow_mut_data()?;
+        let buffer_header = match multisig {
+            Some((threshold, signers)) => {
+                msg!("Multisig: {} of {} signers", threshold, signers.len());
+                AuthorizedBufferHeader::new_multisig(
                     bump_seed,
                     buffer_seed,
-                };
-
-                buffer[0..AUTH_BUFFER_HEADER_SIZE]
-                    .copy_from_slice(&buffer_header.try_to_vec().unwrap());
-                msg!("Authorized buffer len: {}", buffer_size);
-                msg!("Bump seed: {}", bump_seed);
-                msg!("Buffer seed: {}", buffer_seed);
-            }
-            EchoInstruction::AuthorizedEcho { data } => {
-                msg!("Authorized echo");
-                let accounts_iter = &mut accounts.iter();
-                let authorized_buffer = next_account_info(accounts_iter)?;
-                let authority = next_account_info(accounts_iter)?;
-                let buffer = &mut (*authorized_buffer.data).try_borrow_mut().unwrap();
-                let buffer_header =
-                    AuthorizedBufferHeader::try_from_slice(&buffer[..AUTH_BUFFER_HEADER_SIZE])
-                        .unwrap();
-
-                let pda = Pubkey::create_program_address(
-                    &[
-                        b"authority",
-                        authority.key.as_ref(),
-                        &buffer_header.buffer_seed.to_le_bytes(),
-                        &[buffer_header.bump_seed],
-                    ],
-                    _program_id,
+                    buffer_kind,
+                    *authority.key,
+                    threshold,
+                    signers,
                 )
-                .unwrap();
+            }
+            None => {
+                AuthorizedBufferHeader::new(bump_seed, buffer_seed, buffer_kind, *authority.key)
+            }
+        };
 
-                if pda != *authorized_buffer.key {
-                    msg!("authorized buffer is not correct pda");
-                    return Err(ProgramError::IllegalOwner);
-                }
+        buffer_header.pack(buffer)?;
+        msg!("Authorized buffer len: {}", buffer_size);
+        msg!("Bump seed: {}", bump_seed);
+        msg!("Buffer seed: {}", buffer_seed);
+        msg!("Buffer kind: {:?}", buffer_kind);
+        EchoEvent::BufferCreated {
+            buffer: *authorized_buffer.key,
+            authority: *authority.key,
+            buffer_seed,
+            buffer_kind,
+            size: buffer_size as u64,
+            threshold: buffer_header.threshold,
+            signers: buffer_header.signers.clone(),
+        }
+        .emit();
+        Ok(())
+    }
 
-                let buffer_data = &mut buffer[AUTH_BUFFER_HEADER_SIZE..];
+    fn process_authorized_echo(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        data: Vec<u8>,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let delegate_record = next_account_info(accounts_iter).ok();
 
-                for index in 0..buffer_data.len() {
-                    buffer_data[index] = match index < data.len() {
-                        true => data[index],
-                        false => 0,
-                    };
-                }
-            }
-            EchoInstruction::InitializeVendingMachine { price, buffer_size } => {
-                msg!("Initialize vending machine");
-                let accounts_iter = &mut accounts.iter();
-                let vending_machine_buffer = next_account_info(accounts_iter)?;
-                let vending_machine_mint = next_account_info(accounts_iter)?;
-                let payer = next_account_info(accounts_iter)?;
-                let system_program = next_account_info(accounts_iter)?;
-
-                msg!("price: {} , buffer_size: {}", price, buffer_size);
-
-                let _mint =
-                    Mint::unpack_unchecked(&vending_machine_mint.data.borrow()).map_err(|e| {
-                        msg!("invalid account");
-                        return e;
-                    });
-
-                let (pda, bump) = Pubkey::find_program_address(
-                    &[
-                        b"vending_machine",
-                        vending_machine_mint.key.as_ref(),
-                        &price.to_le_bytes(),
-                    ],
-                    _program_id,
-                );
+        let mut buffer_header = assert_authorized_writer(
+            program_id,
+            authorized_buffer,
+            authority,
+            delegate_record,
+            accounts,
+        )?;
+
+        write_authorized(authorized_buffer, &mut buffer_header, authority.key, &data)
+    }
+
+    fn process_authorized_echo_cas(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        data: Vec<u8>,
+        expected_seq: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let delegate_record = next_account_info(accounts_iter).ok();
+
+        let mut buffer_header = assert_authorized_writer(
+            program_id,
+            authorized_buffer,
+            authority,
+            delegate_record,
+            accounts,
+        )?;
+        if buffer_header.write_seq != expected_seq {
+            msg!(
+                "Buffer is at write sequence {}, expected {}",
+                buffer_header.write_seq,
+                expected_seq
+            );
+            return Err(EchoError::WriteSequenceMismatch.into());
+        }
+
+        write_authorized(authorized_buffer, &mut buffer_header, authority.key, &data)?;
+        set_return_data(&buffer_header.write_seq.to_le_bytes());
+        Ok(())
+    }
+
+    fn process_close_authorized_buffer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let destination = next_account_info(accounts_iter)?;
+
+        let buffer_header =
+            assert_authorized_buffer(program_id, authorized_buffer, authority, accounts)?;
+        assert_not_frozen(&buffer_header)?;
+        if buffer_header.delegate_count > 0 {
+            msg!(
+                "{} delegates have to be revoked before closing",
+                buffer_header.delegate_count
+            );
+            return Err(EchoError::BufferHasDelegates.into());
+        }
+        assert_writable(destination)?;
+        if destination.key == authorized_buffer.key {
+            msg!("destination can not be the authorized buffer");
+            return Err(EchoError::InvalidDestination.into());
+        }
+
+        authorized_buffer.try_borrow_mut_data()?.fill(0);
+
+        let buffer_lamports = authorized_buffer.lamports();
+        **destination.try_borrow_mut_lamports()? = destination
+            .lamports()
+            .checked_add(buffer_lamports)
+            .ok_or(EchoError::Overflow)?;
+        **authorized_buffer.try_borrow_mut_lamports()? = 0;
+
+        msg!(
+            "Closed authorized buffer, reclaimed {} lamports",
+            buffer_lamports
+        );
+        EchoEvent::BufferClosed {
+            buffer: *authorized_buffer.key,
+            destination: *destination.key,
+            lamports: buffer_lamports,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_resize_authorized_buffer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_size: usize,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        let buffer_header =
+            assert_authorized_buffer(program_id, authorized_buffer, authority, accounts)?;
+        assert_not_frozen(&buffer_header)?;
+        assert_writable(authority)?;
+        assert_system_program(system_program)?;
+        if buffer_header.buffer_kind == BufferKind::Ring {
+            msg!("Ring buffers can not be resized");
+            return Err(EchoError::InvalidBufferKind.into());
+        }
+
+        let header_size = buffer_header.header_size as usize;
+        if new_size <= header_size {
+            msg!(
+                "Invalid buffer length {}, must be greater than header size {}",
+                new_size,
+                header_size
+            );
+            return Err(EchoError::BufferTooSmall.into());
+        }
+        if new_size - header_size < buffer_header.cursor as usize {
+            msg!(
+                "Buffer can not shrink below its {} appended bytes",
+                buffer_header.cursor
+            );
+            return Err(EchoError::BufferTooSmall.into());
+        }
+        let old_size = authorized_buffer.data_len();
+        if new_size > old_size + MAX_PERMITTED_DATA_INCREASE {
+            msg!(
+                "Buffer can grow by at most {} bytes per instruction",
+                MAX_PERMITTED_DATA_INCREASE
+            );
+            return Err(EchoError::ResizeTooLarge.into());
+        }
+
+        let rent_exempt_lamports = Rent::get()?.minimum_balance(new_size);
+        let buffer_lamports = authorized_buffer.lamports();
+        if rent_exempt_lamports > buffer_lamports {
+            invoke(
+                &system_instruction::transfer(
+                    authority.key,
+                    authorized_buffer.key,
+                    rent_exempt_lamports - buffer_lamports,
+                ),
+                &[
+                    authority.clone(),
+                    authorized_buffer.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        } else {
+            let refund = buffer_lamports - rent_exempt_lamports;
+            **authorized_buffer.try_borrow_mut_lamports()? = rent_exempt_lamports;
+            **authority.try_borrow_mut_lamports()? = authority
+                .lamports()
+                .checked_add(refund)
+                .ok_or(EchoError::Overflow)?;
+        }
+
+        authorized_buffer.realloc(new_size, false)?;
+        msg!(
+            "Resized authorized buffer from {} to {}",
+            old_size,
+            new_size
+        );
+        EchoEvent::BufferResized {
+            buffer: *authorized_buffer.key,
+            old_size: old_size as u64,
+            new_size: new_size as u64,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_propose_authority(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_authority: Pubkey,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+
+        let mut buffer_header =
+            assert_authorized_buffer(program_id, authorized_buffer, authority, accounts)?;
+        buffer_header.pending_authority = Some(new_authority);
+        buffer_header.pack(&mut authorized_buffer.try_borrow_mut_data()?)?;
+
+        msg!("Proposed authority: {}", new_authority);
+        EchoEvent::AuthorityProposed {
+            buffer: *authorized_buffer.key,
+            pending_authority: new_authority,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let new_authority = next_account_info(accounts_iter)?;
+
+        assert_writable(authorized_buffer)?;
+        let mut buffer_header = assert_authorized_buffer_pda(program_id, authorized_buffer)?;
+        assert_signer(new_authority)?;
+        if buffer_header.pending_authority != Some(*new_authority.key) {
+            msg!("{} is not the pending authority", new_authority.key);
+            return Err(EchoError::InvalidPendingAuthority.into());
+        }
+
+        // the new authority takes over alone, also from a multisig,
+        // and delegates of the previous authority can no longer write
+        buffer_header.authority = *new_authority.key;
+        buffer_header.pending_authority = None;
+        buffer_header.threshold = 0;
+        buffer_header.signers.clear();
+        buffer_header.authority_generation += 1;
+        buffer_header.pack(&mut authorized_buffer.try_borrow_mut_data()?)?;
+
+        msg!("New authority: {}", new_authority.key);
+        EchoEvent::AuthorityChanged {
+            buffer: *authorized_buffer.key,
+            authority: *new_authority.key,
+            authority_generation: buffer_header.authority_generation,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_echo_at(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        offset: usize,
+        data: Vec<u8>,
+    ) -> ProgramResult {
+        let account_iter = &mut accounts.iter();
+        let echo_buffer = next_account_info(account_iter)?;
+        assert_echo_buffer(program_id, echo_buffer)?;
+
+        let buffer = &mut echo_buffer.try_borrow_mut_data()?;
+        write_at(&mut buffer[ECHO_BUFFER_HEADER_SIZE..], offset, &data)?;
+        EchoEvent::data_written(echo_buffer.key, None, offset, &data).emit();
+        Ok(())
+    }
+
+    fn process_authorized_echo_at(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        offset: usize,
+        data: Vec<u8>,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+
+        let mut buffer_header =
+            assert_authorized_buffer(program_id, authorized_buffer, authority, accounts)?;
+        assert_not_frozen(&buffer_header)?;
+        if buffer_header.buffer_kind != BufferKind::Overwrite {
+            msg!(
+                "Offset writes are not supported by {:?} buffers",
+                buffer_header.buffer_kind
+            );
+            return Err(EchoError::InvalidBufferKind.into());
+        }
+
+        let buffer = &mut authorized_buffer.try_borrow_mut_data()?;
+        write_at(
+            &mut buffer[buffer_header.header_size as usize..],
+            offset,
+            &data,
+        )?;
+        record_write(
+            authorized_buffer.key,
+            &mut buffer_header,
+            authority.key,
+            offset,
+            &data,
+        )?;
+        buffer_header.pack(buffer)?;
+        Ok(())
+    }
+
+    fn process_register_consumer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        consumer: Pubkey,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let ring_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let consumer_cursor = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        let buffer_header = assert_authorized_buffer_pda(program_id, ring_buffer)?;
+        assert_buffer_authority(&buffer_header, authority, accounts)?;
+        assert_writable(authority)?;
+        if buffer_header.buffer_kind != BufferKind::Ring {
+            msg!("Consumers can only be registered on ring buffers");
+            return Err(EchoError::InvalidBufferKind.into());
+        }
+        assert_writable(consumer_cursor)?;
+        assert_system_program(system_program)?;
+
+        let bump_seed = assert_derivation(
+            program_id,
+            consumer_cursor,
+            &[
+                CONSUMER_CURSOR_SEED,
+                ring_buffer.key.as_ref(),
+                consumer.as_ref(),
+            ],
+        )?;
+
+        invoke_signed(
+            &system_instruction::create_account(
+                authority.key,
+                consumer_cursor.key,
+                Rent::get()?.minimum_balance(CONSUMER_CURSOR_SIZE),
+                CONSUMER_CURSOR_SIZE as u64,
+                program_id,
+            ),
+            &[
+                authority.clone(),
+                consumer_cursor.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                CONSUMER_CURSOR_SEED,
+                ring_buffer.key.as_ref(),
+                consumer.as_ref(),
+                &[bump_seed],
+            ]],
+        )?;
+
+        // new consumers start at the oldest stored message
+        let cursor = ConsumerCursor {
+            account_type: AccountType::ConsumerCursor,
+            version: AccountType::ConsumerCursor.current_version(),
+            bump_seed,
+            buffer: *ring_buffer.key,
+            consumer,
+            cursor: buffer_header.first_seq,
+        };
+        cursor.pack(&mut consumer_cursor.try_borrow_mut_data()?)?;
+
+        msg!("Registered consumer {} at {}", consumer, cursor.cursor);
+        EchoEvent::ConsumerRegistered {
+            buffer: *ring_buffer.key,
+            consumer,
+            cursor: cursor.cursor,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_consume(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        up_to_seq: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let ring_buffer = next_account_info(accounts_iter)?;
+        let consumer_cursor = next_account_info(accounts_iter)?;
+        let consumer = next_account_info(accounts_iter)?;
+
+        let buffer_header = assert_authorized_buffer_pda(program_id, ring_buffer)?;
+        assert_writable(consumer_cursor)?;
+        assert_owned_by(consumer_cursor, program_id)?;
+        assert_signer(consumer)?;
+
+        let mut cursor = ConsumerCursor::unpack(&consumer_cursor.try_borrow_data()?)?;
+        if cursor.buffer != *ring_buffer.key || cursor.consumer != *consumer.key {
+            msg!("cursor does not belong to the consumer of this buffer");
+            return Err(EchoError::InvalidAuthority.into());
+        }
+        assert_program_address(
+            program_id,
+            consumer_cursor,
+            &[
+                CONSUMER_CURSOR_SEED,
+                ring_buffer.key.as_ref(),
+                consumer.key.as_ref(),
+                &[cursor.bump_seed],
+            ],
+        )?;
+        if up_to_seq >= buffer_header.next_seq || up_to_seq < cursor.cursor {
+            msg!(
+                "Can not consume up to {}, cursor is at {} and the next message is {}",
+                up_to_seq,
+                cursor.cursor,
+                buffer_header.next_seq
+            );
+            return Err(EchoError::InvalidSequence.into());
+        }
+
+        cursor.cursor = up_to_seq + 1;
+        cursor.pack(&mut consumer_cursor.try_borrow_mut_data()?)?;
+
+        msg!("Consumer {} cursor at {}", consumer.key, cursor.cursor);
+        EchoEvent::Consumed {
+            buffer: *ring_buffer.key,
+            consumer: *consumer.key,
+            cursor: cursor.cursor,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_close_consumer(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let ring_buffer = next_account_info(accounts_iter)?;
+        let consumer_cursor = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let destination = next_account_info(accounts_iter)?;
 
-                if pda != *vending_machine_buffer.key {
-                    msg!("vending machine buffer pubkey is not equal to expected pda");
-                    return Err(ProgramError::InvalidAccountData);
+        assert_writable(consumer_cursor)?;
+        assert_owned_by(consumer_cursor, program_id)?;
+        let cursor = ConsumerCursor::unpack(&consumer_cursor.try_borrow_data()?)?;
+        if cursor.buffer != *ring_buffer.key {
+            msg!("cursor does not belong to this buffer");
+            return Err(EchoError::InvalidAuthority.into());
+        }
+        assert_program_address(
+            program_id,
+            consumer_cursor,
+            &[
+                CONSUMER_CURSOR_SEED,
+                ring_buffer.key.as_ref(),
+                cursor.consumer.as_ref(),
+                &[cursor.bump_seed],
+            ],
+        )?;
+        // the consumer can reclaim its cursor even after the buffer is closed,
+        // the buffer authority only while the buffer exists
+        if *authority.key == cursor.consumer {
+            assert_signer(authority)?;
+        } else {
+            let buffer_header = assert_authorized_buffer_pda(program_id, ring_buffer)?;
+            assert_buffer_authority(&buffer_header, authority, accounts)?;
+        }
+        assert_writable(destination)?;
+        if destination.key == consumer_cursor.key {
+            msg!("destination can not be the consumer cursor");
+            return Err(EchoError::InvalidDestination.into());
+        }
+
+        consumer_cursor.try_borrow_mut_data()?.fill(0);
+
+        let cursor_lamports = consumer_cursor.lamports();
+        **destination.try_borrow_mut_lamports()? = destination
+            .lamports()
+            .checked_add(cursor_lamports)
+            .ok_or(EchoError::Overflow)?;
+        **consumer_cursor.try_borrow_mut_lamports()? = 0;
+
+        msg!(
+            "Closed consumer {} cursor, reclaimed {} lamports",
+            cursor.consumer,
+            cursor_lamports
+        );
+        EchoEvent::ConsumerClosed {
+            buffer: *ring_buffer.key,
+            consumer: cursor.consumer,
+            destination: *destination.key,
+            lamports: cursor_lamports,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_initialize_vending_machine(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        price: u64,
+        buffer_size: usize,
+    ) -> ProgramResult {
+        if buffer_size <= VENDING_MACHINE_BUFFER_HEADER_SIZE {
+            msg!(
+                "Invalid buffer length {}, must be greater than header size {}",
+                buffer_size,
+                VENDING_MACHINE_BUFFER_HEADER_SIZE
+            );
+            return Err(EchoError::BufferTooSmall.into());
+        }
+        let accounts_iter = &mut accounts.iter();
+        let vending_machine_buffer = next_account_info(accounts_iter)?;
+        let vending_machine_mint = next_account_info(accounts_iter)?;
+        let payer = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        assert_writable(vending_machine_buffer)?;
+        assert_owned_by(vending_machine_mint, &spl_token::id())?;
+        assert_signer(payer)?;
+        assert_writable(payer)?;
+        assert_system_program(system_program)?;
+
+        msg!("price: {} , buffer_size: {}", price, buffer_size);
+
+        Mint::unpack(&vending_machine_mint.data.borrow()).map_err(|_| {
+            msg!("invalid mint account");
+            EchoError::InvalidMint
+        })?;
+
+        let price_b = price.to_le_bytes();
+        let bump = assert_derivation(
+            program_id,
+            vending_machine_buffer,
+            &[
+                VENDING_MACHINE_SEED,
+                vending_machine_mint.key.as_ref(),
+                &price_b,
+            ],
+        )?;
+
+        let create_vending_machine_buffer = system_instruction::create_account(
+            payer.key,
+            vending_machine_buffer.key,
+            Rent::get()?.minimum_balance(buffer_size),
+            buffer_size as u64,
+            program_id,
+        );
+
+        invoke_signed(
+            &create_vending_machine_buffer,
+            &[
+                payer.clone(),
+                system_program.clone(),
+                vending_machine_buffer.clone(),
+            ],
+            &[&[
+                VENDING_MACHINE_SEED,
+                vending_machine_mint.key.as_ref(),
+                &price_b,
+                &[bump],
+            ]],
+        )?;
+
+        let buffer = &mut vending_machine_buffer.try_borrow_mut_data()?;
+        let vending_machine_buffer_header = VendingMachineBufferHeader::new(bump, price);
+
+        vending_machine_buffer_header.pack(buffer)?;
+
+        msg!("Vending machine buffer len: {}", buffer_size);
+        msg!("Bump seed: {}", bump);
+        msg!("Buffer price: {}", price);
+        EchoEvent::VendingMachineInitialized {
+            buffer: *vending_machine_buffer.key,
+            mint: *vending_machine_mint.key,
+            price,
+            size: buffer_size as u64,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_vending_machine_echo(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        data: Vec<u8>,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let vending_machine_buffer = next_account_info(accounts_iter)?;
+        let vending_machine_mint = next_account_info(accounts_iter)?;
+        let user = next_account_info(accounts_iter)?;
+        let user_token_account = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+
+        assert_writable(vending_machine_buffer)?;
+        assert_owned_by(vending_machine_buffer, program_id)?;
+        assert_writable(vending_machine_mint)?;
+        assert_owned_by(vending_machine_mint, &spl_token::id())?;
+        assert_signer(user)?;
+        assert_writable(user_token_account)?;
+        assert_owned_by(user_token_account, &spl_token::id())?;
+        assert_token_program(token_program)?;
+
+        if vending_machine_buffer.data_len() <= VENDING_MACHINE_BUFFER_HEADER_SIZE {
+            msg!("vending machine buffer is too small to hold a header");
+            return Err(EchoError::BufferTooSmall.into());
+        }
+        let buffer_header =
+            VendingMachineBufferHeader::unpack(&vending_machine_buffer.try_borrow_data()?)?;
+
+        assert_program_address(
+            program_id,
+            vending_machine_buffer,
+            &[
+                VENDING_MACHINE_SEED,
+                vending_machine_mint.key.as_ref(),
+                &buffer_header.price.to_le_bytes(),
+                &[buffer_header.bump_seed],
+            ],
+        )?;
+
+        let token_account = TokenAccount::unpack(&user_token_account.data.borrow())
+            .map_err(|_| EchoError::InvalidTokenAccount)?;
+        if token_account.mint != *vending_machine_mint.key {
+            msg!("token account mint is not the vending machine mint");
+            return Err(EchoError::InvalidMint.into());
+        }
+        if token_account.amount < buffer_header.price {
+            msg!(
+                "insufficient tokens: {} < price {}",
+                token_account.amount,
+                buffer_header.price
+            );
+            return Err(EchoError::InsufficientTokens.into());
+        }
+
+        let burn_ix = spl_token::instruction::burn(
+            token_program.key,
+            user_token_account.key,
+            vending_machine_mint.key,
+            user.key,
+            &[],
+            buffer_header.price,
+        )?;
+
+        invoke(
+            &burn_ix,
+            &[
+                user_token_account.clone(),
+                vending_machine_mint.clone(),
+                user.clone(),
+                token_program.clone(),
+            ],
+        )?;
+        msg!("Burned {} tokens", buffer_header.price);
+        EchoEvent::TokensBurned {
+            buffer: *vending_machine_buffer.key,
+            mint: *vending_machine_mint.key,
+            user: *user.key,
+            amount: buffer_header.price,
+        }
+        .emit();
+
+        let buffer = &mut vending_machine_buffer.try_borrow_mut_data()?;
+        let buffer_data = &mut buffer[VENDING_MACHINE_BUFFER_HEADER_SIZE..];
+
+        for index in 0..buffer_data.len() {
+            buffer_data[index] = match index < data.len() {
+                true => data[index],
+                false => 0,
+            };
+        }
+        let written = data.len().min(buffer_data.len());
+        EchoEvent::data_written(
+            vending_machine_buffer.key,
+            Some(user.key),
+            0,
+            &data[..written],
+        )
+        .emit();
+        Ok(())
+    }
+
+    fn process_migrate_buffer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        account_type: AccountType,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let buffer_account = next_account_info(accounts_iter)?;
+        let payer = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        assert_writable(buffer_account)?;
+        assert_owned_by(buffer_account, program_id)?;
+        assert_signer(payer)?;
+        assert_writable(payer)?;
+        assert_system_program(system_program)?;
+
+        let old_size = buffer_account.data_len();
+        let old_header_data =
+            buffer_account.try_borrow_data()?[..old_size.min(V0_HEADER_SIZE)].to_vec();
+        let old_version = old_header_data.get(1).copied().unwrap_or_default();
+        let is_tagged =
+            match AccountType::try_from_slice(old_header_data.get(..1).unwrap_or_default()) {
+                Ok(tag) => old_version > 0 && old_version <= tag.current_version(),
+                Err(_) => false,
+            };
+
+        // new header and size of the header it replaces
+        let (header, old_header_size) = if is_tagged {
+            if old_header_data[0] != account_type as u8 {
+                msg!("Buffer is not of type {:?}", account_type);
+                return Err(EchoError::InvalidAccountType.into());
+            }
+            if old_version == account_type.current_version() {
+                msg!("Buffer is already at version {}", old_version);
+                return Err(EchoError::AlreadyMigrated.into());
+            }
+            // only authorized buffers have more than one tagged version
+            let (mut buffer_header, old_header_size) =
+                AuthorizedBufferHeader::unpack_any_version(&buffer_account.try_borrow_data()?)?;
+            if old_size <= old_header_size {
+                msg!("buffer is too small to hold a v{} header", old_version);
+                return Err(EchoError::BufferTooSmall.into());
+            }
+            // only multisig headers keep space for their signers
+            buffer_header.version = account_type.current_version();
+            buffer_header.header_size = match buffer_header.is_multisig() {
+                true => multisig_auth_buffer_header_size(buffer_header.signers.len()),
+                false => AUTH_BUFFER_HEADER_SIZE,
+            } as u64;
+            let mut header = vec![0; buffer_header.header_size as usize];
+            buffer_header.pack(&mut header)?;
+            assert_program_address(
+                program_id,
+                buffer_account,
+                &[
+                    AUTHORIZED_BUFFER_SEED,
+                    buffer_header.seed_authority.as_ref(),
+                    &buffer_header.buffer_seed.to_le_bytes(),
+                    &[buffer_header.bump_seed],
+                ],
+            )?;
+            (header, old_header_size)
+        } else {
+            match account_type {
+                // v0 echo buffers are raw payload without any header, they were keypair
+                // accounts, which keeps pdas of other untagged buffers from being taken over
+                AccountType::EchoBuffer => {
+                    assert_signer(buffer_account)?;
+                    let mut header = vec![0; ECHO_BUFFER_HEADER_SIZE];
+                    EchoBufferHeader::new().pack(&mut header)?;
+                    (header, 0)
                 }
+                _ if old_size < V0_HEADER_SIZE => {
+                    msg!("buffer is too small to hold a v0 header");
+                    return Err(EchoError::BufferTooSmall.into());
+                }
+                AccountType::AuthorizedBuffer => {
+                    let v0_header = AuthorizedBufferHeaderV0::try_from_slice(&old_header_data)
+                        .map_err(|_| EchoError::InvalidBufferHeader)?;
+                    assert_program_address(
+                        program_id,
+                        buffer_account,
+                        &[
+                            AUTHORIZED_BUFFER_SEED,
+                            payer.key.as_ref(),
+                            &v0_header.buffer_seed.to_le_bytes(),
+                            &[v0_header.bump_seed],
+                        ],
+                    )?;
+                    let mut header = vec![0; AUTH_BUFFER_HEADER_SIZE];
+                    AuthorizedBufferHeader::new(
+                        v0_header.bump_seed,
+                        v0_header.buffer_seed,
+                        BufferKind::Overwrite,
+                        *payer.key,
+                    )
+                    .pack(&mut header)?;
+                    (header, V0_HEADER_SIZE)
+                }
+                AccountType::VendingMachineBuffer => {
+                    let vending_machine_mint = next_account_info(accounts_iter)?;
+                    let v0_header = VendingMachineBufferHeaderV0::try_from_slice(&old_header_data)
+                        .map_err(|_| EchoError::InvalidBufferHeader)?;
+                    assert_program_address(
+                        program_id,
+                        buffer_account,
+                        &[
+                            VENDING_MACHINE_SEED,
+                            vending_machine_mint.key.as_ref(),
+                            &v0_header.price.to_le_bytes(),
+                            &[v0_header.bump_seed],
+                        ],
+                    )?;
+                    let mut header = vec![0; VENDING_MACHINE_BUFFER_HEADER_SIZE];
+                    VendingMachineBufferHeader::new(v0_header.bump_seed, v0_header.price)
+                        .pack(&mut header)?;
+                    (header, V0_HEADER_SIZE)
+                }
+                _ => {
+                    msg!("Accounts of type {:?} have no v0 layout", account_type);
+                    return Err(EchoError::InvalidAccountType.into());
+                }
+            }
+        };
 
-                let create_vending_machine_buffer = system_instruction::create_account(
+        let new_size = old_size - old_header_size + header.len();
+        let rent_exempt_lamports = Rent::get()?.minimum_balance(new_size);
+        let buffer_lamports = buffer_account.lamports();
+        if rent_exempt_lamports > buffer_lamports {
+            invoke(
+                &system_instruction::transfer(
                     payer.key,
-                    &pda,
-                    Rent::get()?.minimum_balance(buffer_size),
-                    buffer_size as u64,
-                    _program_id,
-                );
+                    buffer_account.key,
+                    rent_exempt_lamports - buffer_lamports,
+                ),
+                &[
+                    payer.clone(),
+                    buffer_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+
+        // the payload moves before a shrinking realloc cuts off its end and after a
+        // growing one made room for it, lamports freed by a smaller header stay in
+        // the buffer until it is closed
+        if new_size < old_size {
+            buffer_account
+                .try_borrow_mut_data()?
+                .copy_within(old_header_size..old_size, header.len());
+            buffer_account.realloc(new_size, false)?;
+        } else {
+            buffer_account.realloc(new_size, false)?;
+            buffer_account
+                .try_borrow_mut_data()?
+                .copy_within(old_header_size..old_size, header.len());
+        }
+        buffer_account.try_borrow_mut_data()?[..header.len()].copy_from_slice(&header);
+
+        msg!(
+            "Migrated buffer to version {}, size {} -> {}",
+            account_type.current_version(),
+            old_size,
+            new_size
+        );
+        EchoEvent::BufferMigrated {
+            buffer: *buffer_account.key,
+            account_type,
+            old_version: if is_tagged { old_version } else { 0 },
+            new_version: account_type.current_version(),
+            size: new_size as u64,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_read_buffer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        offset: usize,
+        len: usize,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let buffer_account = next_account_info(accounts_iter)?;
+        assert_owned_by(buffer_account, program_id)?;
+
+        if len > MAX_RETURN_DATA {
+            msg!(
+                "Read of {} bytes exceeds the return data limit of {}",
+                len,
+                MAX_RETURN_DATA
+            );
+            return Err(EchoError::ReadTooLarge.into());
+        }
 
+        let header_size = match buffer_account.try_borrow_data()?.first() {
+            Some(tag) if *tag == AccountType::AuthorizedBuffer as u8 => {
+                assert_authorized_buffer_pda(program_id, buffer_account)?.header_size as usize
+            }
+            Some(tag) if *tag == AccountType::VendingMachineBuffer as u8 => {
+                VendingMachineBufferHeader::unpack(&buffer_account.try_borrow_data()?)?;
+                VENDING_MACHINE_BUFFER_HEADER_SIZE
+            }
+            Some(tag) if *tag == AccountType::EchoBuffer as u8 => {
+                EchoBufferHeader::unpack(&buffer_account.try_borrow_data()?)?;
+                ECHO_BUFFER_HEADER_SIZE
+            }
+            _ => {
+                msg!("Only echo, authorized and vending machine buffers can be read");
+                return Err(EchoError::InvalidAccountType.into());
+            }
+        };
+
+        let buffer = buffer_account.try_borrow_data()?;
+        let payload = &buffer[header_size..];
+        let end = offset.checked_add(len).ok_or(EchoError::Overflow)?;
+        if end > payload.len() {
+            msg!(
+                "Read of {} bytes at offset {} exceeds payload length {}",
+                len,
+                offset,
+                payload.len()
+            );
+            return Err(EchoError::ReadOutOfBounds.into());
+        }
+        set_return_data(&payload[offset..end]);
+        Ok(())
+    }
+
+    fn process_initialize_echo_buffer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        size: usize,
+        buffer_seed: Option<u64>,
+    ) -> ProgramResult {
+        if size <= ECHO_BUFFER_HEADER_SIZE {
+            msg!(
+                "Invalid buffer length {}, must be greater than header size {}",
+                size,
+                ECHO_BUFFER_HEADER_SIZE
+            );
+            return Err(EchoError::BufferTooSmall.into());
+        }
+        let accounts_iter = &mut accounts.iter();
+        let echo_buffer = next_account_info(accounts_iter)?;
+        let payer = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        assert_writable(echo_buffer)?;
+        assert_signer(payer)?;
+        assert_writable(payer)?;
+        assert_system_program(system_program)?;
+
+        let create_account_ix = system_instruction::create_account(
+            payer.key,
+            echo_buffer.key,
+            Rent::get()?.minimum_balance(size),
+            size as u64,
+            program_id,
+        );
+        let create_account_infos = &[payer.clone(), echo_buffer.clone(), system_program.clone()];
+        match buffer_seed {
+            Some(buffer_seed) => {
+                let buffer_seed_b = buffer_seed.to_le_bytes();
+                let bump_seed = assert_derivation(
+                    program_id,
+                    echo_buffer,
+                    &[ECHO_BUFFER_SEED, payer.key.as_ref(), &buffer_seed_b],
+                )?;
                 invoke_signed(
-                    &create_vending_machine_buffer,
-                    &[
-                        payer.clone(),
-                        system_program.clone(),
-                        vending_machine_buffer.clone(),
-                    ],
+                    &create_account_ix,
+                    create_account_infos,
                     &[&[
-                        b"vending_machine",
-                        vending_machine_mint.key.as_ref(),
-                        &price.to_le_bytes(),
-                        &[bump],
+                        ECHO_BUFFER_SEED,
+                        payer.key.as_ref(),
+                        &buffer_seed_b,
+                        &[bump_seed],
                     ]],
                 )?;
+            }
+            None => {
+                assert_signer(echo_buffer)?;
+                invoke(&create_account_ix, create_account_infos)?;
+            }
+        }
 
-                let buffer = &mut (*vending_machine_buffer.data).borrow_mut();
-                let vending_machine_buffer_header = VendingMachineBufferHeader {
-                    bump_seed: bump,
-                    price: price,
-                };
+        EchoBufferHeader::new().pack(&mut echo_buffer.try_borrow_mut_data()?)?;
+        msg!("Echo buffer len: {}", size);
+        EchoEvent::EchoBufferCreated {
+            buffer: *echo_buffer.key,
+            payer: *payer.key,
+            size: size as u64,
+        }
+        .emit();
+        Ok(())
+    }
 
-                buffer[0..AUTH_BUFFER_HEADER_SIZE]
-                    .copy_from_slice(&vending_machine_buffer_header.try_to_vec().unwrap());
+    fn process_add_delegate(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        delegate: Pubkey,
+        expires_at_slot: u64,
+        max_writes: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let delegate_record = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
 
-                msg!("Vending machine buffer len: {}", buffer_size);
-                msg!("Bump seed: {}", bump);
-                msg!("Buffer price: {}", price);
-            }
-            _ => {
-                msg!("invalid instruction");
-                return Err(ProgramError::InvalidInstructionData);
-            }
+        let mut buffer_header =
+            assert_authorized_buffer(program_id, authorized_buffer, authority, accounts)?;
+        assert_writable(authority)?;
+        assert_writable(delegate_record)?;
+        assert_system_program(system_program)?;
+
+        let bump_seed = assert_derivation(
+            program_id,
+            delegate_record,
+            &[
+                DELEGATE_RECORD_SEED,
+                authorized_buffer.key.as_ref(),
+                delegate.as_ref(),
+            ],
+        )?;
+
+        // adding an existing delegate again replaces its allowance,
+        // records of older versions were never counted
+        let is_counted = if delegate_record.data_is_empty() {
+            invoke_signed(
+                &system_instruction::create_account(
+                    authority.key,
+                    delegate_record.key,
+                    Rent::get()?.minimum_balance(DELEGATE_RECORD_SIZE),
+                    DELEGATE_RECORD_SIZE as u64,
+                    program_id,
+                ),
+                &[
+                    authority.clone(),
+                    delegate_record.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    DELEGATE_RECORD_SEED,
+                    authorized_buffer.key.as_ref(),
+                    delegate.as_ref(),
+                    &[bump_seed],
+                ]],
+            )?;
+            false
+        } else {
+            assert_owned_by(delegate_record, program_id)?;
+            let record = DelegateRecord::unpack_any_version(&delegate_record.try_borrow_data()?)?;
+            record.version == AccountType::DelegateRecord.current_version()
+        };
+        if !is_counted {
+            buffer_header.delegate_count += 1;
+            buffer_header.pack(&mut authorized_buffer.try_borrow_mut_data()?)?;
+        }
+
+        let record = DelegateRecord {
+            account_type: AccountType::DelegateRecord,
+            version: AccountType::DelegateRecord.current_version(),
+            bump_seed,
+            buffer: *authorized_buffer.key,
+            delegate,
+            expires_at_slot,
+            remaining_writes: max_writes,
+            authority_generation: buffer_header.authority_generation,
+        };
+        record.pack(&mut delegate_record.try_borrow_mut_data()?)?;
+
+        msg!(
+            "Delegate {} can write {} times until slot {}",
+            delegate,
+            max_writes,
+            expires_at_slot
+        );
+        EchoEvent::DelegateAdded {
+            buffer: *authorized_buffer.key,
+            delegate,
+            expires_at_slot,
+            max_writes,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_revoke_delegate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+        let delegate_record = next_account_info(accounts_iter)?;
+
+        let mut buffer_header =
+            assert_authorized_buffer(program_id, authorized_buffer, authority, accounts)?;
+        assert_writable(authority)?;
+        assert_writable(delegate_record)?;
+        assert_owned_by(delegate_record, program_id)?;
+        let record = DelegateRecord::unpack_any_version(&delegate_record.try_borrow_data()?)?;
+        if record.buffer != *authorized_buffer.key {
+            msg!("Delegate record does not belong to the buffer");
+            return Err(EchoError::InvalidDelegate.into());
         }
+        // records of older versions were never counted
+        if record.version == AccountType::DelegateRecord.current_version() {
+            buffer_header.delegate_count = buffer_header
+                .delegate_count
+                .checked_sub(1)
+                .ok_or(EchoError::Overflow)?;
+            buffer_header.pack(&mut authorized_buffer.try_borrow_mut_data()?)?;
+        }
+
+        delegate_record.try_borrow_mut_data()?.fill(0);
+        let record_lamports = delegate_record.lamports();
+        **authority.try_borrow_mut_lamports()? = authority
+            .lamports()
+            .checked_add(record_lamports)
+            .ok_or(EchoError::Overflow)?;
+        **delegate_record.try_borrow_mut_lamports()? = 0;
+
+        msg!("Revoked delegate {}", record.delegate);
+        EchoEvent::DelegateRevoked {
+            buffer: *authorized_buffer.key,
+            delegate: record.delegate,
+        }
+        .emit();
         Ok(())
     }
+
+    fn process_propose_write(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        data_hash: [u8; 32],
+        data: Vec<u8>,
+        expires_in_slots: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let proposer = next_account_info(accounts_iter)?;
+        let write_proposal = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        let buffer_header = assert_authorized_buffer_pda(program_id, authorized_buffer)?;
+        assert_not_frozen(&buffer_header)?;
+        assert_signer(proposer)?;
+        assert_writable(proposer)?;
+        if !buffer_header.is_multisig() {
+            msg!("Writes can only be proposed to multisig buffers");
+            return Err(EchoError::InvalidMultisig.into());
+        }
+        if !buffer_header.signers.contains(proposer.key) {
+            msg!("{} is not a signer of the buffer", proposer.key);
+            return Err(EchoError::InvalidAuthority.into());
+        }
+        if hash(&data).to_bytes() != data_hash {
+            msg!("Data does not match the proposed data hash");
+            return Err(EchoError::InvalidProposal.into());
+        }
+        assert_writable(write_proposal)?;
+        assert_system_program(system_program)?;
+
+        let bump_seed = assert_derivation(
+            program_id,
+            write_proposal,
+            &[
+                WRITE_PROPOSAL_SEED,
+                authorized_buffer.key.as_ref(),
+                &data_hash,
+            ],
+        )?;
+
+        let proposal_size = WRITE_PROPOSAL_HEADER_SIZE + data.len();
+        invoke_signed(
+            &system_instruction::create_account(
+                proposer.key,
+                write_proposal.key,
+                Rent::get()?.minimum_balance(proposal_size),
+                proposal_size as u64,
+                program_id,
+            ),
+            &[
+                proposer.clone(),
+                write_proposal.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                WRITE_PROPOSAL_SEED,
+                authorized_buffer.key.as_ref(),
+                &data_hash,
+                &[bump_seed],
+            ]],
+        )?;
+
+        // the proposer approves its own write
+        let expires_at_slot = Clock::get()?
+            .slot
+            .checked_add(expires_in_slots)
+            .ok_or(EchoError::Overflow)?;
+        let proposal = WriteProposal {
+            account_type: AccountType::WriteProposal,
+            version: AccountType::WriteProposal.current_version(),
+            bump_seed,
+            buffer: *authorized_buffer.key,
+            proposer: *proposer.key,
+            data_hash,
+            expires_at_slot,
+            approvals: vec![*proposer.key],
+        };
+        let proposal_data = &mut write_proposal.try_borrow_mut_data()?;
+        proposal.pack(proposal_data)?;
+        proposal_data[WRITE_PROPOSAL_HEADER_SIZE..].copy_from_slice(&data);
+
+        msg!(
+            "Proposed write of {} bytes until slot {}",
+            data.len(),
+            expires_at_slot
+        );
+        EchoEvent::WriteProposed {
+            buffer: *authorized_buffer.key,
+            proposal: *write_proposal.key,
+            proposer: *proposer.key,
+            data_hash,
+            expires_at_slot,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_approve_write(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let write_proposal = next_account_info(accounts_iter)?;
+        let signer = next_account_info(accounts_iter)?;
+
+        let buffer_header = assert_authorized_buffer_pda(program_id, authorized_buffer)?;
+        let mut proposal = assert_write_proposal(program_id, authorized_buffer, write_proposal)?;
+        assert_signer(signer)?;
+        if !buffer_header.signers.contains(signer.key) {
+            msg!("{} is not a signer of the buffer", signer.key);
+            return Err(EchoError::InvalidAuthority.into());
+        }
+        let slot = Clock::get()?.slot;
+        if slot > proposal.expires_at_slot {
+            msg!(
+                "Proposal expired at slot {}, current slot {}",
+                proposal.expires_at_slot,
+                slot
+            );
+            return Err(EchoError::ProposalExpired.into());
+        }
+        if proposal.approvals.contains(signer.key) {
+            msg!("{} already approved the write", signer.key);
+            return Err(EchoError::AlreadyApproved.into());
+        }
+
+        proposal.approvals.push(*signer.key);
+        proposal.pack(&mut write_proposal.try_borrow_mut_data()?)?;
+
+        msg!(
+            "{} of {} required approvals",
+            proposal.approvals.len(),
+            buffer_header.threshold
+        );
+        EchoEvent::WriteApproved {
+            buffer: *authorized_buffer.key,
+            proposal: *write_proposal.key,
+            signer: *signer.key,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_execute_write(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let write_proposal = next_account_info(accounts_iter)?;
+        let proposer = next_account_info(accounts_iter)?;
+
+        assert_writable(authorized_buffer)?;
+        let mut buffer_header = assert_authorized_buffer_pda(program_id, authorized_buffer)?;
+        assert_not_frozen(&buffer_header)?;
+        let proposal = assert_write_proposal(program_id, authorized_buffer, write_proposal)?;
+        assert_writable(proposer)?;
+        if proposal.proposer != *proposer.key {
+            msg!("{} is not the proposer of the write", proposer.key);
+            return Err(EchoError::InvalidProposal.into());
+        }
+        let slot = Clock::get()?.slot;
+        if slot > proposal.expires_at_slot {
+            msg!(
+                "Proposal expired at slot {}, current slot {}",
+                proposal.expires_at_slot,
+                slot
+            );
+            return Err(EchoError::ProposalExpired.into());
+        }
+        if !buffer_header.is_multisig() {
+            msg!("Buffer is no longer a multisig");
+            return Err(EchoError::InvalidMultisig.into());
+        }
+        // approvals of keys no longer in the signer set do not count
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|signer| buffer_header.signers.contains(signer))
+            .count();
+        if approvals < buffer_header.threshold as usize {
+            msg!(
+                "{} of {} required buffer signers approved",
+                approvals,
+                buffer_header.threshold
+            );
+            return Err(EchoError::NotEnoughSigners.into());
+        }
+
+        let data = write_proposal.try_borrow_data()?[WRITE_PROPOSAL_HEADER_SIZE..].to_vec();
+        write_authorized(authorized_buffer, &mut buffer_header, proposer.key, &data)?;
+        close_write_proposal(write_proposal, proposer)
+    }
+
+    fn process_cancel_write(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let write_proposal = next_account_info(accounts_iter)?;
+        let proposer = next_account_info(accounts_iter)?;
+
+        let proposal = assert_write_proposal(program_id, authorized_buffer, write_proposal)?;
+        assert_writable(proposer)?;
+        if proposal.proposer != *proposer.key {
+            msg!("{} is not the proposer of the write", proposer.key);
+            return Err(EchoError::InvalidProposal.into());
+        }
+        if !proposer.is_signer && Clock::get()?.slot <= proposal.expires_at_slot {
+            msg!("Only the proposer can cancel a write before it expires");
+            return Err(EchoError::MissingSigner.into());
+        }
+
+        close_write_proposal(write_proposal, proposer)?;
+        msg!("Cancelled write proposal {}", write_proposal.key);
+        Ok(())
+    }
+
+    fn process_freeze_authorized_buffer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+
+        let mut buffer_header =
+            assert_authorized_buffer(program_id, authorized_buffer, authority, accounts)?;
+        assert_not_frozen(&buffer_header)?;
+        buffer_header.frozen = true;
+        buffer_header.pack(&mut authorized_buffer.try_borrow_mut_data()?)?;
+
+        msg!("Froze buffer at write sequence {}", buffer_header.write_seq);
+        EchoEvent::BufferFrozen {
+            buffer: *authorized_buffer.key,
+        }
+        .emit();
+        Ok(())
+    }
+
+    fn process_initialize_immutable_echo(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        buffer_seed: u64,
+        buffer_size: usize,
+        data: Vec<u8>,
+    ) -> ProgramResult {
+        Self::process_initialize_authorized_echo(
+            program_id,
+            accounts,
+            buffer_seed,
+            buffer_size,
+            BufferKind::Overwrite,
+            None,
+        )?;
+        let accounts_iter = &mut accounts.iter();
+        let authorized_buffer = next_account_info(accounts_iter)?;
+        let authority = next_account_info(accounts_iter)?;
+
+        let buffer = &mut authorized_buffer.try_borrow_mut_data()?;
+        let mut buffer_header = AuthorizedBufferHeader::unpack(buffer)?;
+        write_at(&mut buffer[buffer_header.header_size as usize..], 0, &data)?;
+        record_write(
+            authorized_buffer.key,
+            &mut buffer_header,
+            authority.key,
+            0,
+            &data,
+        )?;
+        buffer_header.frozen = true;
+        buffer_header.pack(buffer)?;
+
+        EchoEvent::BufferFrozen {
+            buffer: *authorized_buffer.key,
+        }
+        .emit();
+        Ok(())
+    }
+}
+
+/// Write `data` into `buffer` starting at `offset`
+fn write_at(buffer: &mut [u8], offset: usize, data: &[u8]) -> ProgramResult {
+    let end = offset.checked_add(data.len()).ok_or(EchoError::Overflow)?;
+    if end > buffer.len() {
+        msg!(
+            "Write of {} bytes at offset {} exceeds buffer length {}",
+            data.len(),
+            offset,
+            buffer.len()
+        );
+        return Err(EchoError::WriteOutOfBounds.into());
+    }
+    buffer[offset..end].copy_from_slice(data);
+    msg!("Wrote {} bytes at offset {}", data.len(), offset);
+    Ok(())
+}
+
+/// Write `data` by `writer` to the payload of an authorized buffer according to its kind
+fn write_authorized(
+    authorized_buffer: &AccountInfo,
+    buffer_header: &mut AuthorizedBufferHeader,
+    writer: &Pubkey,
+    data: &[u8],
+) -> ProgramResult {
+    let buffer = &mut authorized_buffer.try_borrow_mut_data()?;
+    let header_size = buffer_header.header_size as usize;
+    // payload offset and length of the written data
+    let (offset, written) = match buffer_header.buffer_kind {
+        BufferKind::Overwrite => {
+            let buffer_data = &mut buffer[header_size..];
+
+            for index in 0..buffer_data.len() {
+                buffer_data[index] = match index < data.len() {
+                    true => data[index],
+                    false => 0,
+                };
+            }
+            (0, data.len().min(buffer_data.len()))
+        }
+        BufferKind::Append => {
+            let cursor = buffer_header.cursor as usize;
+            let capacity = buffer.len() - header_size;
+            if cursor + data.len() > capacity {
+                msg!(
+                    "Append of {} bytes at cursor {} exceeds capacity {}",
+                    data.len(),
+                    cursor,
+                    capacity
+                );
+                return Err(EchoError::BufferFull.into());
+            }
+            write_at(&mut buffer[header_size..], cursor, data)?;
+            buffer_header.cursor += data.len() as u64;
+            (cursor, data.len())
+        }
+        BufferKind::Ring => {
+            let offset = buffer_header.tail as usize;
+            let seq = push_message(buffer_header, &mut buffer[header_size..], data)?;
+            msg!("Pushed message {}", seq);
+            (offset, data.len())
+        }
+    };
+    record_write(
+        authorized_buffer.key,
+        buffer_header,
+        writer,
+        offset,
+        &data[..written],
+    )?;
+    buffer_header.pack(buffer)
+}
+
+/// Count a write of `data` by `writer` at payload `offset` in the buffer header
+/// and emit its event
+fn record_write(
+    authorized_buffer: &Pubkey,
+    buffer_header: &mut AuthorizedBufferHeader,
+    writer: &Pubkey,
+    offset: usize,
+    data: &[u8],
+) -> ProgramResult {
+    let clock = Clock::get()?;
+    buffer_header.write_seq += 1;
+    buffer_header.last_writer = *writer;
+    buffer_header.last_write_slot = clock.slot;
+    buffer_header.last_write_timestamp = clock.unix_timestamp;
+    buffer_header.last_write_len = data.len() as u64;
+    EchoEvent::data_written(authorized_buffer, Some(writer), offset, data).emit();
+    Ok(())
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use solana_program::clock::Epoch;
-    use std::{borrow::Borrow, mem};
-
-    // #[test]
-    // fn test_initialize_authorize_echo() {
-    //     let program_id = Pubkey::default();
-    //     let authority = Pubkey::default();
-    //     let mut lamports = 0;
-    //     let mut authority_lamports = 10;
-    //     let mut authority_data = vec![0; mem::size_of::<u32>()];
-    //     let buffer_seed: u64 = 3;
-    //     let buffer_size: usize = 8;
-    //     let (authorized_key, bump) = Pubkey::find_program_address(
-    //         &[b"authority", authority.as_ref(), &buffer_seed.to_le_bytes()],
-    //         &program_id,
-    //     );
-    //     let mut data = vec![0; buffer_size];
-
-    //     let authorization_echo_account = AccountInfo::new(
-    //         &authorized_key,
-    //         false,
-    //         true,
-    //         &mut lamports,
-    //         &mut data,
-    //         &authority,
-    //         false,
-    //         Epoch::default(),
-    //     );
-
-    //     let authority_account = AccountInfo::new(
-    //         &authority,
-    //         true,
-    //         false,
-    //         &mut authority_lamports,
-    //         &mut authority_data,
-    //         &program_id,
-    //         false,
-    //         Epoch::default(),
-    //     );
-
-    //     let system_program = AccountInfo::new(
-    //         &system_program::id(),
-    //         true,
-    //         false,
-    //         &mut 0,
-    //         &mut [0; 1],
-    //         &system_program::id(),
-    //         false,
-    //         Epoch::default(),
-    //     );
-
-    //     let accounts = vec![
-    //         authorization_echo_account,
-    //         authority_account,
-    //         system_program,
-    //     ];
-
-    //     let mut instruction_data: Vec<u8> = Vec::new();
-    //     let authorization_echo_instruction = EchoInstruction::InitializeAuthorizedEcho {
-    //         buffer_seed,
-    //         buffer_size,
-    //     };
-    //     authorization_echo_instruction
-    //         .serialize(&mut instruction_data)
-    //         .unwrap();
-
-    //     Processor::process_instruction(&program_id, &accounts, &instruction_data).unwrap();
-    //     println!("data: {:?}", (*accounts[0].data).borrow());
-    // }
-    // #[test]
-    // fn test_sanity() {
-    //     let program_id = Pubkey::default();
-    //     let key = Pubkey::default();
-    //     let mut lamports = 0;
-    //     let mut data = vec![0; mem::size_of::<u32>()];
-    //     let owner = Pubkey::default();
-
-    //     let echo_account = AccountInfo::new(
-    //         &key,
-    //         false,
-    //         true,
-    //         &mut lamports,
-    //         &mut data,
-    //         &owner,
-    //         false,
-    //         Epoch::default(),
-    //     );
-
-    //     let mut instruction_data: Vec<u8> = Vec::new();
-    //     //instruction_data.push(0);
-    //     let echo_instruction = EchoInstruction::Echo {
-    //         data: vec![0, 1, 1, 1, 2],
-    //     };
-    //     echo_instruction.serialize(&mut instruction_data).unwrap();
-
-    //     let instruction = EchoInstruction::try_from_slice(&instruction_data);
-    //     println!("{:?}", instruction);
-    //     let accounts = vec![echo_account];
-
-    //     Processor::process_instruction(&program_id, &accounts, &instruction_data).unwrap();
-
-    //     println!("data. {:?}", (*accounts[0].data).borrow());
-    //     assert!(false)
-    // }
+/// Zero a write proposal and return its lamports to the proposer
+fn close_write_proposal(write_proposal: &AccountInfo, proposer: &AccountInfo) -> ProgramResult {
+    write_proposal.try_borrow_mut_data()?.fill(0);
+    let proposal_lamports = write_proposal.lamports();
+    **proposer.try_borrow_mut_lamports()? = proposer
+        .lamports()
+        .checked_add(proposal_lamports)
+        .ok_or(EchoError::Overflow)?;
+    **write_proposal.try_borrow_mut_lamports()? = 0;
+    Ok(())
 }
@@ -0,0 +1,157 @@
+//! Ring of length prefixed messages stored in the payload of an authorized buffer
+//!
+//! Every message is a little endian `u32` length followed by the message bytes,
+//! both wrapping around the end of the payload. `head` points at the oldest
+//! message, `tail` at the next free byte, and the number of stored messages
+//! is `next_seq - first_seq`.
+use std::mem::size_of;
+
+use solana_program::{msg, program_error::ProgramError};
+
+use crate::error::EchoError;
+use crate::state::AuthorizedBufferHeader;
+
+/// Size of the length prefix of every message
+pub const MESSAGE_PREFIX_SIZE: usize = size_of::<u32>();
+
+/// Copy `out.len()` bytes starting at `offset`, wrapping around the end of `payload`
+fn read_wrapping(payload: &[u8], offset: usize, out: &mut [u8]) {
+    for (index, byte) in out.iter_mut().enumerate() {
+        *byte = payload[(offset + index) % payload.len()];
+    }
+}
+
+/// Copy `data` to `payload` starting at `offset`, wrapping around the end of `payload`
+fn write_wrapping(payload: &mut [u8], offset: usize, data: &[u8]) {
+    let capacity = payload.len();
+    for (index, byte) in data.iter().enumerate() {
+        payload[(offset + index) % capacity] = *byte;
+    }
+}
+
+/// Number of payload bytes occupied by stored messages
+fn used(header: &AuthorizedBufferHeader, capacity: usize) -> usize {
+    if header.first_seq == header.next_seq {
+        return 0;
+    }
+    let (head, tail) = (header.head as usize, header.tail as usize);
+    if tail > head {
+        tail - head
+    } else {
+        capacity - head + tail
+    }
+}
+
+/// Length of the message starting at `offset`
+fn message_len(payload: &[u8], offset: usize) -> usize {
+    let mut prefix = [0; MESSAGE_PREFIX_SIZE];
+    read_wrapping(payload, offset, &mut prefix);
+    u32::from_le_bytes(prefix) as usize
+}
+
+/// Push `message` to the ring, evicting the oldest messages until it fits
+///
+/// returns the sequence number of the message
+pub fn push_message(
+    header: &mut AuthorizedBufferHeader,
+    payload: &mut [u8],
+    message: &[u8],
+) -> Result<u64, ProgramError> {
+    let capacity = payload.len();
+    let message_size = MESSAGE_PREFIX_SIZE + message.len();
+    if message_size > capacity {
+        msg!(
+            "Message of {} bytes does not fit in ring of {} bytes",
+            message.len(),
+            capacity
+        );
+        return Err(EchoError::MessageTooLarge.into());
+    }
+
+    while capacity - used(header, capacity) < message_size {
+        let evicted_size = MESSAGE_PREFIX_SIZE + message_len(payload, header.head as usize);
+        header.head = ((header.head as usize + evicted_size) % capacity) as u64;
+        header.first_seq += 1;
+    }
+
+    let tail = header.tail as usize;
+    write_wrapping(payload, tail, &(message.len() as u32).to_le_bytes());
+    write_wrapping(payload, tail + MESSAGE_PREFIX_SIZE, message);
+    header.tail = ((tail + message_size) % capacity) as u64;
+    if header.first_seq == header.next_seq {
+        header.head = tail as u64;
+    }
+
+    let seq = header.next_seq;
+    header.next_seq += 1;
+    Ok(seq)
+}
+
+/// All messages stored in the ring, oldest first, with their sequence numbers
+pub fn read_messages(header: &AuthorizedBufferHeader, payload: &[u8]) -> Vec<(u64, Vec<u8>)> {
+    let mut messages = Vec::new();
+    let mut offset = header.head as usize;
+    for seq in header.first_seq..header.next_seq {
+        let mut message = vec![0; message_len(payload, offset)];
+        read_wrapping(payload, offset + MESSAGE_PREFIX_SIZE, &mut message);
+        offset = (offset + MESSAGE_PREFIX_SIZE + message.len()) % payload.len();
+        messages.push((seq, message));
+    }
+    messages
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::BufferKind;
+    use solana_program::pubkey::Pubkey;
+
+    fn ring_header() -> AuthorizedBufferHeader {
+        AuthorizedBufferHeader::new(0, 0, BufferKind::Ring, Pubkey::default())
+    }
+
+    #[test]
+    fn test_push_and_read() {
+        let mut header = ring_header();
+        let mut payload = vec![0; 32];
+        assert_eq!(push_message(&mut header, &mut payload, b"abc").unwrap(), 0);
+        assert_eq!(push_message(&mut header, &mut payload, b"de").unwrap(), 1);
+        assert_eq!(
+            read_messages(&header, &payload),
+            vec![(0, b"abc".to_vec()), (1, b"de".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_evicts_oldest_and_wraps() {
+        let mut header = ring_header();
+        let mut payload = vec![0; 20];
+        for (seq, message) in [b"aaaaa", b"bbbbb", b"ccccc", b"ddddd"].iter().enumerate() {
+            assert_eq!(
+                push_message(&mut header, &mut payload, *message).unwrap(),
+                seq as u64
+            );
+        }
+        // every message takes 9 bytes, so only the last two fit
+        assert_eq!(header.first_seq, 2);
+        assert_eq!(
+            read_messages(&header, &payload),
+            vec![(2, b"ccccc".to_vec()), (3, b"ddddd".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_message_too_large() {
+        let mut header = ring_header();
+        let mut payload = vec![0; 8];
+        assert_eq!(
+            push_message(&mut header, &mut payload, b"abcde").unwrap_err(),
+            EchoError::MessageTooLarge.into()
+        );
+        assert!(push_message(&mut header, &mut payload, b"abcd").is_ok());
+        assert_eq!(
+            read_messages(&header, &payload),
+            vec![(0, b"abcd".to_vec())]
+        );
+    }
+}
@@ -1,21 +1,934 @@
 use std::mem::size_of;
 
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::error::EchoError;
+
+/// Seed prefix of authorized buffer addresses
+pub const AUTHORIZED_BUFFER_SEED: &[u8] = b"authority";
+/// Seed prefix of vending machine buffer addresses
+pub const VENDING_MACHINE_SEED: &[u8] = b"vending_machine";
+/// Seed prefix of ring buffer consumer cursor addresses
+pub const CONSUMER_CURSOR_SEED: &[u8] = b"consumer";
+/// Seed prefix of program derived echo buffer addresses
+pub const ECHO_BUFFER_SEED: &[u8] = b"echo";
+/// Seed prefix of authorized buffer delegate record addresses
+pub const DELEGATE_RECORD_SEED: &[u8] = b"delegate";
+/// Seed prefix of multisig write proposal addresses
+pub const WRITE_PROPOSAL_SEED: &[u8] = b"proposal";
+
+/// Type tag at the start of every program owned account
+///
+/// Legacy v0 buffers have no tag and start with their bump seed instead,
+/// canonical bump seeds are practically never small enough to collide with a tag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub enum AccountType {
+    Uninitialized,
+    AuthorizedBuffer,
+    VendingMachineBuffer,
+    ConsumerCursor,
+    EchoBuffer,
+    DelegateRecord,
+    WriteProposal,
+}
+
+impl AccountType {
+    /// Current layout version of accounts of this type
+    pub fn current_version(self) -> u8 {
+        match self {
+            AccountType::AuthorizedBuffer => 7,
+            AccountType::DelegateRecord => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Size of the account type and layout version prefix
+pub const ACCOUNT_TAG_SIZE: usize = size_of::<u8>() + size_of::<u8>();
+
+/// Check the account type and layout version prefix of `data`
+pub fn check_account_type(data: &[u8], account_type: AccountType) -> ProgramResult {
+    if data.len() < ACCOUNT_TAG_SIZE {
+        return Err(EchoError::BufferTooSmall.into());
+    }
+    if data[0] != account_type as u8 {
+        msg!("Account is not of type {:?}", account_type);
+        return Err(EchoError::InvalidAccountType.into());
+    }
+    if data[1] != account_type.current_version() {
+        msg!(
+            "Account layout version {} is not the current version {}",
+            data[1],
+            account_type.current_version()
+        );
+        return Err(EchoError::UnsupportedVersion.into());
+    }
+    Ok(())
+}
+
+/// Deserialize an account of `account_type` from the first `size` bytes of `data`
+fn unpack_account<T: BorshDeserialize>(
+    data: &[u8],
+    account_type: AccountType,
+    size: usize,
+) -> Result<T, ProgramError> {
+    check_account_type(data, account_type)?;
+    if data.len() < size {
+        return Err(EchoError::BufferTooSmall.into());
+    }
+    T::deserialize(&mut &data[..size]).map_err(|_| EchoError::InvalidBufferHeader.into())
+}
+
+/// Deserialize an account of `account_type` stored at any of its layout versions
+///
+/// The stored prefix of `version_size(version)` bytes is padded with zeros and read
+/// as the current layout, returns the account and the size of the stored prefix
+fn unpack_any_version<T: BorshDeserialize>(
+    data: &[u8],
+    account_type: AccountType,
+    version_size: fn(u8) -> Option<usize>,
+    size: usize,
+) -> Result<(T, usize), ProgramError> {
+    if data.len() < ACCOUNT_TAG_SIZE {
+        return Err(EchoError::BufferTooSmall.into());
+    }
+    if data[0] != account_type as u8 {
+        msg!("Account is not of type {:?}", account_type);
+        return Err(EchoError::InvalidAccountType.into());
+    }
+    let stored_size = version_size(data[1]).ok_or(EchoError::UnsupportedVersion)?;
+    if data.len() < stored_size {
+        return Err(EchoError::BufferTooSmall.into());
+    }
+    let mut padded = vec![0; size];
+    padded[..stored_size].copy_from_slice(&data[..stored_size]);
+    padded[1] = account_type.current_version();
+    Ok((unpack_account(&padded, account_type, size)?, stored_size))
+}
+
+/// Serialize an account into the first `size` bytes of `data`
+fn pack_account<T: BorshSerialize>(account: &T, data: &mut [u8], size: usize) -> ProgramResult {
+    if data.len() < size {
+        return Err(EchoError::BufferTooSmall.into());
+    }
+    let header = &mut data[..size];
+    header.fill(0);
+    account.serialize(&mut &mut header[..])?;
+    Ok(())
+}
 
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
 pub struct EchoBuffer {
     pub data: Vec<u8>,
 }
+
+/// Header of a plain echo buffer, the payload starts after it
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct EchoBufferHeader {
+    pub account_type: AccountType,
+    pub version: u8,
+}
+
+impl EchoBufferHeader {
+    /// Header of a new echo buffer
+    pub fn new() -> Self {
+        Self {
+            account_type: AccountType::EchoBuffer,
+            version: AccountType::EchoBuffer.current_version(),
+        }
+    }
+
+    /// Deserialize the header from the start of the buffer
+    pub fn unpack(buffer: &[u8]) -> Result<Self, ProgramError> {
+        unpack_account(buffer, AccountType::EchoBuffer, ECHO_BUFFER_HEADER_SIZE)
+    }
+
+    /// Serialize the header into the start of the buffer
+    pub fn pack(&self, buffer: &mut [u8]) -> ProgramResult {
+        pack_account(self, buffer, ECHO_BUFFER_HEADER_SIZE)
+    }
+}
+
+impl Default for EchoBufferHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub const ECHO_BUFFER_HEADER_SIZE: usize = ACCOUNT_TAG_SIZE;
+/// How `AuthorizedEcho` writes to an authorized buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub enum BufferKind {
+    /// every write replaces the buffer content
+    Overwrite,
+    /// every write is appended after the previous one
+    Append,
+    /// every write pushes a length prefixed message,
+    /// evicting the oldest messages when full
+    Ring,
+}
+
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
 pub struct AuthorizedBufferHeader {
+    pub account_type: AccountType,
+    pub version: u8,
     pub bump_seed: u8,
     pub buffer_seed: u64,
+    pub buffer_kind: BufferKind,
+    /// offset into the payload where the next append is written
+    pub cursor: u64,
+    /// ring: offset into the payload of the oldest message
+    pub head: u64,
+    /// ring: offset into the payload where the next message is written
+    pub tail: u64,
+    /// ring: sequence number of the oldest message
+    pub first_seq: u64,
+    /// ring: sequence number of the next message
+    pub next_seq: u64,
+    /// key the buffer address is derived from
+    pub seed_authority: Pubkey,
+    /// key allowed to write to and administer the buffer
+    pub authority: Pubkey,
+    /// authority proposed by the current authority, has to accept to take over
+    pub pending_authority: Option<Pubkey>,
+    /// multisig: number of `signers` that have to sign instead of `authority`,
+    /// 0 if the buffer is controlled by `authority` alone
+    pub threshold: u8,
+    /// multisig: keys allowed to write to and administer the buffer together
+    pub signers: Vec<Pubkey>,
+    /// number of writes to the payload, compared by `AuthorizedEchoCas`
+    pub write_seq: u64,
+    /// authority, signer, delegate or proposer of the last write
+    pub last_writer: Pubkey,
+    /// slot of the last write
+    pub last_write_slot: u64,
+    /// unix timestamp of the last write
+    pub last_write_timestamp: i64,
+    /// number of bytes written by the last write
+    pub last_write_len: u64,
+    /// frozen buffers reject every write, resize and close
+    pub frozen: bool,
+    /// incremented whenever the authority changes, delegate records
+    /// added in an earlier generation can no longer write
+    pub authority_generation: u64,
+    /// delegate records of the buffer, it can only be closed once all are revoked
+    pub delegate_count: u64,
+    /// size of the header, the payload starts after it. Fixed when the buffer is
+    /// created, a multisig header keeps the space of its signers when they are cleared
+    pub header_size: u64,
+}
+
+impl AuthorizedBufferHeader {
+    /// Header of a new buffer derived from `authority` and `buffer_seed`
+    pub fn new(
+        bump_seed: u8,
+        buffer_seed: u64,
+        buffer_kind: BufferKind,
+        authority: Pubkey,
+    ) -> Self {
+        Self {
+            account_type: AccountType::AuthorizedBuffer,
+            version: AccountType::AuthorizedBuffer.current_version(),
+            bump_seed,
+            buffer_seed,
+            buffer_kind,
+            cursor: 0,
+            head: 0,
+            tail: 0,
+            first_seq: 0,
+            next_seq: 0,
+            seed_authority: authority,
+            authority,
+            pending_authority: None,
+            threshold: 0,
+            signers: Vec::new(),
+            write_seq: 0,
+            last_writer: Pubkey::default(),
+            last_write_slot: 0,
+            last_write_timestamp: 0,
+            last_write_len: 0,
+            frozen: false,
+            authority_generation: 0,
+            delegate_count: 0,
+            header_size: AUTH_BUFFER_HEADER_SIZE as u64,
+        }
+    }
+
+    /// Header of a new buffer derived from `seed_authority` and `buffer_seed`,
+    /// controlled by `threshold` of `signers`
+    pub fn new_multisig(
+        bump_seed: u8,
+        buffer_seed: u64,
+        buffer_kind: BufferKind,
+        seed_authority: Pubkey,
+        threshold: u8,
+        signers: Vec<Pubkey>,
+    ) -> Self {
+        Self {
+            authority: Pubkey::default(),
+            threshold,
+            header_size: multisig_auth_buffer_header_size(signers.len()) as u64,
+            signers,
+            ..Self::new(bump_seed, buffer_seed, buffer_kind, seed_authority)
+        }
+    }
+
+    /// Whether the buffer is controlled by a multisig instead of a single authority
+    pub fn is_multisig(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// Deserialize the header from the start of the buffer
+    pub fn unpack(buffer: &[u8]) -> Result<Self, ProgramError> {
+        let buffer_header: Self = unpack_account(
+            buffer,
+            AccountType::AuthorizedBuffer,
+            buffer.len().min(MAX_AUTH_BUFFER_HEADER_SIZE),
+        )?;
+        let header_size = buffer_header.header_size as usize;
+        if !(AUTH_BUFFER_HEADER_SIZE..=MAX_AUTH_BUFFER_HEADER_SIZE).contains(&header_size) {
+            msg!("Invalid authorized buffer header size {}", header_size);
+            return Err(EchoError::InvalidBufferHeader.into());
+        }
+        if buffer.len() < header_size {
+            return Err(EchoError::BufferTooSmall.into());
+        }
+        Ok(buffer_header)
+    }
+
+    /// Deserialize a header of any tagged layout version from the start of the buffer
+    ///
+    /// returns the header, with the fields its version lacks at their defaults,
+    /// and the size of the header in the buffer
+    pub fn unpack_any_version(buffer: &[u8]) -> Result<(Self, usize), ProgramError> {
+        if buffer.get(1) == Some(&AccountType::AuthorizedBuffer.current_version()) {
+            let buffer_header = Self::unpack(buffer)?;
+            let header_size = buffer_header.header_size as usize;
+            return Ok((buffer_header, header_size));
+        }
+        let (mut buffer_header, header_size): (Self, usize) = unpack_any_version(
+            buffer,
+            AccountType::AuthorizedBuffer,
+            auth_buffer_header_size,
+            MAX_AUTH_BUFFER_HEADER_SIZE,
+        )?;
+        buffer_header.version = buffer[1];
+        buffer_header.header_size = header_size as u64;
+        Ok((buffer_header, header_size))
+    }
+
+    /// Serialize the header into the start of the buffer
+    pub fn pack(&self, buffer: &mut [u8]) -> ProgramResult {
+        pack_account(self, buffer, self.header_size as usize)
+    }
 }
 
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
 pub struct VendingMachineBufferHeader {
+    pub account_type: AccountType,
+    pub version: u8,
     pub bump_seed: u8,
     pub price: u64,
 }
 
-pub const AUTH_BUFFER_HEADER_SIZE: usize = size_of::<u8>() + size_of::<u64>();
+impl VendingMachineBufferHeader {
+    /// Header of a new vending machine buffer
+    pub fn new(bump_seed: u8, price: u64) -> Self {
+        Self {
+            account_type: AccountType::VendingMachineBuffer,
+            version: AccountType::VendingMachineBuffer.current_version(),
+            bump_seed,
+            price,
+        }
+    }
+
+    /// Deserialize the header from the start of the buffer
+    pub fn unpack(buffer: &[u8]) -> Result<Self, ProgramError> {
+        unpack_account(
+            buffer,
+            AccountType::VendingMachineBuffer,
+            VENDING_MACHINE_BUFFER_HEADER_SIZE,
+        )
+    }
+
+    /// Serialize the header into the start of the buffer
+    pub fn pack(&self, buffer: &mut [u8]) -> ProgramResult {
+        pack_account(self, buffer, VENDING_MACHINE_BUFFER_HEADER_SIZE)
+    }
+}
+
+/// Maximum number of signers of a multisig authorized buffer
+pub const MAX_SIGNERS: usize = 11;
+
+/// Size of the header of an authorized buffer controlled by a single authority,
+/// the payload starts after it
+pub const AUTH_BUFFER_HEADER_SIZE: usize = V1_AUTH_BUFFER_HEADER_SIZE
+    + size_of::<u8>()
+    + size_of::<u32>()
+    + size_of::<u64>()
+    + size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<i64>()
+    + size_of::<u64>()
+    + size_of::<bool>()
+    + size_of::<u64>()
+    + size_of::<u64>()
+    + size_of::<u64>();
+
+/// Size of the header of a multisig authorized buffer of `signer_count` signers
+pub const fn multisig_auth_buffer_header_size(signer_count: usize) -> usize {
+    AUTH_BUFFER_HEADER_SIZE + signer_count * size_of::<Pubkey>()
+}
+
+/// Size of the largest authorized buffer header, of a multisig of `MAX_SIGNERS`
+pub const MAX_AUTH_BUFFER_HEADER_SIZE: usize = multisig_auth_buffer_header_size(MAX_SIGNERS);
+
+/// Space reserved for the authorized buffer header before the header size was recorded
+/// and signer space was only reserved for multisig buffers
+pub const V6_AUTH_BUFFER_HEADER_SIZE: usize =
+    V5_AUTH_BUFFER_HEADER_SIZE + size_of::<u64>() + size_of::<u64>();
+
+/// Space reserved for the authorized buffer header before delegate records were
+/// bound to the authority generation
+pub const V5_AUTH_BUFFER_HEADER_SIZE: usize = V4_AUTH_BUFFER_HEADER_SIZE + size_of::<bool>();
+
+/// Space reserved for the authorized buffer header before buffers could be frozen
+pub const V4_AUTH_BUFFER_HEADER_SIZE: usize = V3_AUTH_BUFFER_HEADER_SIZE
+    + size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<i64>()
+    + size_of::<u64>();
+
+/// Space reserved for the authorized buffer header before last write metadata was added
+pub const V3_AUTH_BUFFER_HEADER_SIZE: usize = V2_AUTH_BUFFER_HEADER_SIZE + size_of::<u64>();
+
+/// Space reserved for the authorized buffer header before the write sequence was added
+pub const V2_AUTH_BUFFER_HEADER_SIZE: usize = V1_AUTH_BUFFER_HEADER_SIZE
+    + size_of::<u8>()
+    + (size_of::<u32>() + MAX_SIGNERS * size_of::<Pubkey>());
+
+/// Space reserved for the authorized buffer header before multisig signers were added
+pub const V1_AUTH_BUFFER_HEADER_SIZE: usize = ACCOUNT_TAG_SIZE
+    + size_of::<u8>()
+    + size_of::<u64>()
+    + size_of::<u8>()
+    + size_of::<u64>()
+    + 4 * size_of::<u64>()
+    + size_of::<Pubkey>()
+    + size_of::<Pubkey>()
+    + (1 + size_of::<Pubkey>());
+
+/// Space reserved for the authorized buffer header at layout `version`,
+/// none for the current version, whose header records its own size
+///
+/// Every version only appends fields whose all zero encoding is their default,
+/// so an older header followed by zeros reads as a header of the current version.
+pub fn auth_buffer_header_size(version: u8) -> Option<usize> {
+    match version {
+        1 => Some(V1_AUTH_BUFFER_HEADER_SIZE),
+        2 => Some(V2_AUTH_BUFFER_HEADER_SIZE),
+        3 => Some(V3_AUTH_BUFFER_HEADER_SIZE),
+        4 => Some(V4_AUTH_BUFFER_HEADER_SIZE),
+        5 => Some(V5_AUTH_BUFFER_HEADER_SIZE),
+        6 => Some(V6_AUTH_BUFFER_HEADER_SIZE),
+        _ => None,
+    }
+}
+
+pub const VENDING_MACHINE_BUFFER_HEADER_SIZE: usize =
+    ACCOUNT_TAG_SIZE + size_of::<u8>() + size_of::<u64>();
+
+/// Read position of a consumer of a ring buffer
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct ConsumerCursor {
+    pub account_type: AccountType,
+    pub version: u8,
+    pub bump_seed: u8,
+    pub buffer: Pubkey,
+    pub consumer: Pubkey,
+    /// sequence number of the next message the consumer has not consumed
+    pub cursor: u64,
+}
+
+impl ConsumerCursor {
+    /// Deserialize the cursor from the account data
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        unpack_account(data, AccountType::ConsumerCursor, CONSUMER_CURSOR_SIZE)
+    }
+
+    /// Serialize the cursor into the account data
+    pub fn pack(&self, data: &mut [u8]) -> ProgramResult {
+        pack_account(self, data, CONSUMER_CURSOR_SIZE)
+    }
+}
+
+pub const CONSUMER_CURSOR_SIZE: usize = ACCOUNT_TAG_SIZE
+    + size_of::<u8>()
+    + size_of::<Pubkey>()
+    + size_of::<Pubkey>()
+    + size_of::<u64>();
+
+/// Writer allowed to `AuthorizedEcho` on behalf of the buffer authority
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct DelegateRecord {
+    pub account_type: AccountType,
+    pub version: u8,
+    pub bump_seed: u8,
+    pub buffer: Pubkey,
+    pub delegate: Pubkey,
+    /// last slot the delegate can write in
+    pub expires_at_slot: u64,
+    /// writes left before the delegate has to be added again
+    pub remaining_writes: u64,
+    /// authority generation of the buffer the delegate was added in
+    pub authority_generation: u64,
+}
+
+impl DelegateRecord {
+    /// Deserialize the record from the account data
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        unpack_account(data, AccountType::DelegateRecord, DELEGATE_RECORD_SIZE)
+    }
+
+    /// Deserialize a record of any layout version from the account data,
+    /// records of older versions are not bound to an authority generation
+    pub fn unpack_any_version(data: &[u8]) -> Result<Self, ProgramError> {
+        let (mut record, _): (Self, usize) = unpack_any_version(
+            data,
+            AccountType::DelegateRecord,
+            delegate_record_size,
+            DELEGATE_RECORD_SIZE,
+        )?;
+        record.version = data[1];
+        Ok(record)
+    }
+
+    /// Serialize the record into the account data
+    pub fn pack(&self, data: &mut [u8]) -> ProgramResult {
+        pack_account(self, data, DELEGATE_RECORD_SIZE)
+    }
+}
+
+pub const DELEGATE_RECORD_SIZE: usize = V1_DELEGATE_RECORD_SIZE + size_of::<u64>();
+
+/// Size of delegate records before they were bound to an authority generation
+pub const V1_DELEGATE_RECORD_SIZE: usize = ACCOUNT_TAG_SIZE
+    + size_of::<u8>()
+    + size_of::<Pubkey>()
+    + size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<u64>();
+
+/// Size of a delegate record at layout `version`
+pub fn delegate_record_size(version: u8) -> Option<usize> {
+    match version {
+        1 => Some(V1_DELEGATE_RECORD_SIZE),
+        2 => Some(DELEGATE_RECORD_SIZE),
+        _ => None,
+    }
+}
+
+/// Write to a multisig authorized buffer collecting approvals of its signers,
+/// the proposed data is the payload after the header
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct WriteProposal {
+    pub account_type: AccountType,
+    pub version: u8,
+    pub bump_seed: u8,
+    pub buffer: Pubkey,
+    /// buffer signer that proposed the write, receives the rent when the proposal is closed
+    pub proposer: Pubkey,
+    /// sha256 of the proposed data
+    pub data_hash: [u8; 32],
+    /// last slot the write can be approved and executed in
+    pub expires_at_slot: u64,
+    /// buffer signers that approved the write
+    pub approvals: Vec<Pubkey>,
+}
+
+impl WriteProposal {
+    /// Deserialize the proposal header from the account data
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        unpack_account(data, AccountType::WriteProposal, WRITE_PROPOSAL_HEADER_SIZE)
+    }
+
+    /// Serialize the proposal header into the account data
+    pub fn pack(&self, data: &mut [u8]) -> ProgramResult {
+        pack_account(self, data, WRITE_PROPOSAL_HEADER_SIZE)
+    }
+}
+
+/// Space reserved for the write proposal header, the proposed data starts after it
+pub const WRITE_PROPOSAL_HEADER_SIZE: usize = ACCOUNT_TAG_SIZE
+    + size_of::<u8>()
+    + size_of::<Pubkey>()
+    + size_of::<Pubkey>()
+    + 32
+    + size_of::<u64>()
+    + (size_of::<u32>() + MAX_SIGNERS * size_of::<Pubkey>());
+
+/// Header of authorized buffers created before accounts were tagged
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct AuthorizedBufferHeaderV0 {
+    pub bump_seed: u8,
+    pub buffer_seed: u64,
+}
+
+/// Header of vending machine buffers created before accounts were tagged
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct VendingMachineBufferHeaderV0 {
+    pub bump_seed: u8,
+    pub price: u64,
+}
+
+pub const V0_HEADER_SIZE: usize = size_of::<u8>() + size_of::<u64>();
+
+/// Address and bump seed of the authorized buffer of `authority` and `buffer_seed`
+pub fn find_authorized_buffer_address(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    buffer_seed: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            AUTHORIZED_BUFFER_SEED,
+            authority.as_ref(),
+            &buffer_seed.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Address and bump seed of the record of `delegate` on the authorized buffer `buffer`
+pub fn find_delegate_record_address(
+    program_id: &Pubkey,
+    buffer: &Pubkey,
+    delegate: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[DELEGATE_RECORD_SEED, buffer.as_ref(), delegate.as_ref()],
+        program_id,
+    )
+}
+
+/// Address and bump seed of the proposal to write data with `data_hash` to `buffer`
+pub fn find_write_proposal_address(
+    program_id: &Pubkey,
+    buffer: &Pubkey,
+    data_hash: &[u8; 32],
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[WRITE_PROPOSAL_SEED, buffer.as_ref(), data_hash],
+        program_id,
+    )
+}
+
+/// Address and bump seed of the echo buffer of `payer` and `buffer_seed`
+pub fn find_echo_buffer_address(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    buffer_seed: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ECHO_BUFFER_SEED, payer.as_ref(), &buffer_seed.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Address and bump seed of the vending machine buffer of `mint` and `price`
+pub fn find_vending_machine_address(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    price: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[VENDING_MACHINE_SEED, mint.as_ref(), &price.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Address and bump seed of the cursor of `consumer` on the ring buffer `buffer`
+pub fn find_consumer_cursor_address(
+    program_id: &Pubkey,
+    buffer: &Pubkey,
+    consumer: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[CONSUMER_CURSOR_SEED, buffer.as_ref(), consumer.as_ref()],
+        program_id,
+    )
+}
+
+/// Payload of a buffer rendered for humans
+#[cfg(not(target_os = "solana"))]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct Payload {
+    pub len: usize,
+    pub hex: String,
+    pub base64: String,
+    /// payload without trailing zero bytes, if it is valid utf8
+    pub utf8: Option<String>,
+}
+
+#[cfg(not(target_os = "solana"))]
+impl Payload {
+    pub fn new(payload: &[u8]) -> Self {
+        let end = payload
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |index| index + 1);
+        Self {
+            len: payload.len(),
+            hex: payload.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            base64: base64::encode(payload),
+            utf8: std::str::from_utf8(&payload[..end]).ok().map(String::from),
+        }
+    }
+}
+
+/// Any account owned by the echo program, decoded by [`decode_account`]
+#[cfg(not(target_os = "solana"))]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum XboothAccount {
+    AuthorizedBuffer {
+        version: u8,
+        bump_seed: u8,
+        buffer_seed: u64,
+        buffer_kind: String,
+        cursor: u64,
+        head: u64,
+        tail: u64,
+        first_seq: u64,
+        next_seq: u64,
+        seed_authority: String,
+        authority: String,
+        pending_authority: Option<String>,
+        threshold: u8,
+        signers: Vec<String>,
+        write_seq: u64,
+        last_writer: String,
+        last_write_slot: u64,
+        last_write_timestamp: i64,
+        last_write_len: u64,
+        frozen: bool,
+        authority_generation: u64,
+        delegate_count: u64,
+        payload: Payload,
+    },
+    VendingMachineBuffer {
+        version: u8,
+        bump_seed: u8,
+        price: u64,
+        payload: Payload,
+    },
+    ConsumerCursor {
+        version: u8,
+        bump_seed: u8,
+        buffer: String,
+        consumer: String,
+        cursor: u64,
+    },
+    EchoBuffer {
+        version: u8,
+        payload: Payload,
+    },
+    DelegateRecord {
+        version: u8,
+        bump_seed: u8,
+        buffer: String,
+        delegate: String,
+        expires_at_slot: u64,
+        remaining_writes: u64,
+        authority_generation: u64,
+    },
+    WriteProposal {
+        version: u8,
+        bump_seed: u8,
+        buffer: String,
+        proposer: String,
+        data_hash: String,
+        expires_at_slot: u64,
+        approvals: Vec<String>,
+        payload: Payload,
+    },
+    /// account that failed to decode, such as an untagged legacy echo buffer
+    Unknown {
+        data: Payload,
+    },
+}
+
+#[cfg(not(target_os = "solana"))]
+impl XboothAccount {
+    /// Pretty printed json of the account
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// Decode the data of any account owned by the echo program
+#[cfg(not(target_os = "solana"))]
+pub fn decode_account(data: &[u8]) -> XboothAccount {
+    let tag = data.first().copied();
+    if tag == Some(AccountType::AuthorizedBuffer as u8) {
+        if let Ok((header, header_size)) = AuthorizedBufferHeader::unpack_any_version(data) {
+            return XboothAccount::AuthorizedBuffer {
+                version: header.version,
+                bump_seed: header.bump_seed,
+                buffer_seed: header.buffer_seed,
+                buffer_kind: format!("{:?}", header.buffer_kind),
+                cursor: header.cursor,
+                head: header.head,
+                tail: header.tail,
+                first_seq: header.first_seq,
+                next_seq: header.next_seq,
+                seed_authority: header.seed_authority.to_string(),
+                authority: header.authority.to_string(),
+                pending_authority: header.pending_authority.map(|key| key.to_string()),
+                threshold: header.threshold,
+                signers: header.signers.iter().map(|key| key.to_string()).collect(),
+                write_seq: header.write_seq,
+                last_writer: header.last_writer.to_string(),
+                last_write_slot: header.last_write_slot,
+                last_write_timestamp: header.last_write_timestamp,
+                last_write_len: header.last_write_len,
+                frozen: header.frozen,
+                authority_generation: header.authority_generation,
+                delegate_count: header.delegate_count,
+                payload: Payload::new(&data[header_size..]),
+            };
+        }
+    }
+    if tag == Some(AccountType::VendingMachineBuffer as u8) {
+        if let Ok(header) = VendingMachineBufferHeader::unpack(data) {
+            return XboothAccount::VendingMachineBuffer {
+                version: header.version,
+                bump_seed: header.bump_seed,
+                price: header.price,
+                payload: Payload::new(&data[VENDING_MACHINE_BUFFER_HEADER_SIZE..]),
+            };
+        }
+    }
+    if tag == Some(AccountType::ConsumerCursor as u8) {
+        if let Ok(cursor) = ConsumerCursor::unpack(data) {
+            return XboothAccount::ConsumerCursor {
+                version: cursor.version,
+                bump_seed: cursor.bump_seed,
+                buffer: cursor.buffer.to_string(),
+                consumer: cursor.consumer.to_string(),
+                cursor: cursor.cursor,
+            };
+        }
+    }
+    if tag == Some(AccountType::DelegateRecord as u8) {
+        if let Ok(record) = DelegateRecord::unpack_any_version(data) {
+            return XboothAccount::DelegateRecord {
+                version: record.version,
+                bump_seed: record.bump_seed,
+                buffer: record.buffer.to_string(),
+                delegate: record.delegate.to_string(),
+                expires_at_slot: record.expires_at_slot,
+                remaining_writes: record.remaining_writes,
+                authority_generation: record.authority_generation,
+            };
+        }
+    }
+    if tag == Some(AccountType::WriteProposal as u8) {
+        if let Ok(proposal) = WriteProposal::unpack(data) {
+            return XboothAccount::WriteProposal {
+                version: proposal.version,
+                bump_seed: proposal.bump_seed,
+                buffer: proposal.buffer.to_string(),
+                proposer: proposal.proposer.to_string(),
+                data_hash: proposal
+                    .data_hash
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect(),
+                expires_at_slot: proposal.expires_at_slot,
+                approvals: proposal
+                    .approvals
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect(),
+                payload: Payload::new(&data[WRITE_PROPOSAL_HEADER_SIZE..]),
+            };
+        }
+    }
+    if tag == Some(AccountType::EchoBuffer as u8) {
+        if let Ok(header) = EchoBufferHeader::unpack(data) {
+            return XboothAccount::EchoBuffer {
+                version: header.version,
+                payload: Payload::new(&data[ECHO_BUFFER_HEADER_SIZE..]),
+            };
+        }
+    }
+    XboothAccount::Unknown {
+        data: Payload::new(data),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_authorized_buffer() {
+        let authority = Pubkey::new_unique();
+        let mut data = vec![0; AUTH_BUFFER_HEADER_SIZE + 4];
+        AuthorizedBufferHeader::new(255, 7, BufferKind::Append, authority)
+            .pack(&mut data)
+            .unwrap();
+        data[AUTH_BUFFER_HEADER_SIZE..].copy_from_slice(b"hi\0\0");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&decode_account(&data).to_json()).unwrap();
+        assert_eq!(json["type"], "authorized_buffer");
+        assert_eq!(json["buffer_kind"], "Append");
+        assert_eq!(json["authority"], authority.to_string());
+        assert_eq!(json["pending_authority"], serde_json::Value::Null);
+        assert_eq!(json["threshold"], 0);
+        assert_eq!(json["signers"], serde_json::json!([]));
+        assert_eq!(json["last_writer"], Pubkey::default().to_string());
+        assert_eq!(json["last_write_len"], 0);
+        assert_eq!(json["payload"]["hex"], "68690000");
+        assert_eq!(json["payload"]["base64"], "aGkAAA==");
+        assert_eq!(json["payload"]["utf8"], "hi");
+    }
+
+    #[test]
+    fn test_decode_older_authorized_buffer() {
+        let authority = Pubkey::new_unique();
+        let mut header = AuthorizedBufferHeader::new(255, 7, BufferKind::Overwrite, authority);
+        header.version = 1;
+        let mut data = vec![0; AUTH_BUFFER_HEADER_SIZE];
+        header.pack(&mut data).unwrap();
+        data.truncate(V1_AUTH_BUFFER_HEADER_SIZE);
+        data.extend_from_slice(b"hi");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&decode_account(&data).to_json()).unwrap();
+        assert_eq!(json["type"], "authorized_buffer");
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["authority"], authority.to_string());
+        assert_eq!(json["write_seq"], 0);
+        assert_eq!(json["frozen"], false);
+        assert_eq!(json["payload"]["utf8"], "hi");
+    }
+
+    #[test]
+    fn test_decode_unknown_account() {
+        assert_eq!(
+            decode_account(&[0xff, 0x01]),
+            XboothAccount::Unknown {
+                data: Payload {
+                    len: 2,
+                    hex: "ff01".to_string(),
+                    base64: "/wE=".to_string(),
+                    utf8: None,
+                }
+            }
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, msg,
+    program_error::ProgramError, pubkey::Pubkey, system_program, sysvar::Sysvar,
+};
+
+use crate::error::EchoError;
+use crate::state::{
+    check_account_type, AccountType, AuthorizedBufferHeader, DelegateRecord, EchoBufferHeader,
+    WriteProposal, AUTHORIZED_BUFFER_SEED, DELEGATE_RECORD_SEED, ECHO_BUFFER_HEADER_SIZE,
+    MAX_SIGNERS, WRITE_PROPOSAL_SEED,
+};
+
+/// Account must have signed the transaction
+pub fn assert_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        msg!("{} must be a signer", account.key);
+        return Err(EchoError::MissingSigner.into());
+    }
+    Ok(())
+}
+
+/// Account must be writable
+pub fn assert_writable(account: &AccountInfo) -> ProgramResult {
+    if !account.is_writable {
+        msg!("{} must be writable", account.key);
+        return Err(EchoError::AccountNotWritable.into());
+    }
+    Ok(())
+}
+
+/// Account must be owned by `owner`
+pub fn assert_owned_by(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner != owner {
+        msg!(
+            "{} is owned by {}, expected {}",
+            account.key,
+            account.owner,
+            owner
+        );
+        return Err(EchoError::IncorrectOwner.into());
+    }
+    Ok(())
+}
+
+/// Account must be the canonical program derived address of `seeds`
+///
+/// returns the bump seed
+pub fn assert_derivation(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    seeds: &[&[u8]],
+) -> Result<u8, ProgramError> {
+    let (pda, bump_seed) = Pubkey::find_program_address(seeds, program_id);
+    if pda != *account.key {
+        msg!("{} is not the expected pda {}", account.key, pda);
+        return Err(EchoError::InvalidBufferPda.into());
+    }
+    Ok(bump_seed)
+}
+
+/// Account must be the program address of `seeds`, where the last seed is the bump seed
+pub fn assert_program_address(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let pda = Pubkey::create_program_address(seeds, program_id)
+        .map_err(|_| EchoError::InvalidBufferPda)?;
+    if pda != *account.key {
+        msg!("{} is not the expected pda {}", account.key, pda);
+        return Err(EchoError::InvalidBufferPda.into());
+    }
+    Ok(())
+}
+
+/// Account must be the system program
+pub fn assert_system_program(account: &AccountInfo) -> ProgramResult {
+    if !system_program::check_id(account.key) {
+        msg!("{} is not the system program", account.key);
+        return Err(EchoError::IncorrectSystemProgram.into());
+    }
+    Ok(())
+}
+
+/// Account must be the spl token program
+pub fn assert_token_program(account: &AccountInfo) -> ProgramResult {
+    if !spl_token::check_id(account.key) {
+        msg!("{} is not the spl token program", account.key);
+        return Err(EchoError::IncorrectTokenProgram.into());
+    }
+    Ok(())
+}
+
+/// Authorized buffer must be a writable, program owned pda
+/// and its current `authority` or enough of its multisig `signers` must have signed
+///
+/// returns the buffer header
+pub fn assert_authorized_buffer(
+    program_id: &Pubkey,
+    authorized_buffer: &AccountInfo,
+    authority: &AccountInfo,
+    signers: &[AccountInfo],
+) -> Result<AuthorizedBufferHeader, ProgramError> {
+    assert_writable(authorized_buffer)?;
+    let buffer_header = assert_authorized_buffer_pda(program_id, authorized_buffer)?;
+    assert_buffer_authority(&buffer_header, authority, signers)?;
+    Ok(buffer_header)
+}
+
+/// `authority` must have signed and be the authority of the buffer,
+/// or for multisig buffers be one of at least `threshold` buffer signers in `signers`
+pub fn assert_buffer_authority(
+    buffer_header: &AuthorizedBufferHeader,
+    authority: &AccountInfo,
+    signers: &[AccountInfo],
+) -> ProgramResult {
+    assert_signer(authority)?;
+    if !buffer_header.is_multisig() {
+        if buffer_header.authority != *authority.key {
+            msg!(
+                "{} is not the authority {} of the buffer",
+                authority.key,
+                buffer_header.authority
+            );
+            return Err(EchoError::InvalidAuthority.into());
+        }
+        return Ok(());
+    }
+
+    if !buffer_header.signers.contains(authority.key) {
+        msg!("{} is not a signer of the buffer", authority.key);
+        return Err(EchoError::InvalidAuthority.into());
+    }
+    let approvals = buffer_header
+        .signers
+        .iter()
+        .filter(|signer| {
+            signers
+                .iter()
+                .any(|account| account.is_signer && account.key == *signer)
+        })
+        .count();
+    if approvals < buffer_header.threshold as usize {
+        msg!(
+            "{} of {} required buffer signers signed",
+            approvals,
+            buffer_header.threshold
+        );
+        return Err(EchoError::NotEnoughSigners.into());
+    }
+    Ok(())
+}
+
+/// Authorized buffer must not be frozen
+pub fn assert_not_frozen(buffer_header: &AuthorizedBufferHeader) -> ProgramResult {
+    if buffer_header.frozen {
+        msg!("Buffer is frozen");
+        return Err(EchoError::BufferFrozen.into());
+    }
+    Ok(())
+}
+
+/// Multisig of `threshold` out of `signers` must have distinct signers,
+/// at most `MAX_SIGNERS`, and a threshold between 1 and the number of signers
+pub fn assert_multisig(threshold: u8, signers: &[Pubkey]) -> ProgramResult {
+    if signers.len() > MAX_SIGNERS {
+        msg!(
+            "{} signers exceed the maximum of {}",
+            signers.len(),
+            MAX_SIGNERS
+        );
+        return Err(EchoError::InvalidMultisig.into());
+    }
+    if threshold == 0 || threshold as usize > signers.len() {
+        msg!(
+            "Threshold {} must be between 1 and the number of signers {}",
+            threshold,
+            signers.len()
+        );
+        return Err(EchoError::InvalidMultisig.into());
+    }
+    for (index, signer) in signers.iter().enumerate() {
+        if signers[..index].contains(signer) {
+            msg!("{} is listed more than once", signer);
+            return Err(EchoError::InvalidMultisig.into());
+        }
+    }
+    Ok(())
+}
+
+/// Authorized buffer must be a writable, program owned pda
+/// that is not frozen, and either its current `authority`, enough of its
+/// multisig `signers` or a delegate with a record must have signed
+///
+/// A delegate uses up one write of its record, which must not be expired
+/// and must have been added by the current authority.
+///
+/// returns the buffer header
+pub fn assert_authorized_writer(
+    program_id: &Pubkey,
+    authorized_buffer: &AccountInfo,
+    writer: &AccountInfo,
+    delegate_record: Option<&AccountInfo>,
+    signers: &[AccountInfo],
+) -> Result<AuthorizedBufferHeader, ProgramError> {
+    assert_writable(authorized_buffer)?;
+    let buffer_header = assert_authorized_buffer_pda(program_id, authorized_buffer)?;
+    assert_not_frozen(&buffer_header)?;
+    assert_signer(writer)?;
+    let authority_check = assert_buffer_authority(&buffer_header, writer, signers);
+    if authority_check.is_ok() {
+        return Ok(buffer_header);
+    }
+    // co-signers of a multisig follow the writer, delegate records never sign
+    let delegate_record = match delegate_record {
+        Some(delegate_record) if !delegate_record.is_signer => delegate_record,
+        _ => return authority_check.map(|_| buffer_header),
+    };
+
+    assert_writable(delegate_record)?;
+    assert_owned_by(delegate_record, program_id)?;
+    let mut record = DelegateRecord::unpack(&delegate_record.try_borrow_data()?)?;
+    if record.buffer != *authorized_buffer.key || record.delegate != *writer.key {
+        msg!("{} is not a delegate of the buffer", writer.key);
+        return Err(EchoError::InvalidDelegate.into());
+    }
+    let record_pda = Pubkey::create_program_address(
+        &[
+            DELEGATE_RECORD_SEED,
+            authorized_buffer.key.as_ref(),
+            writer.key.as_ref(),
+            &[record.bump_seed],
+        ],
+        program_id,
+    )
+    .map_err(|_| EchoError::InvalidDelegate)?;
+    if record_pda != *delegate_record.key {
+        msg!(
+            "{} is not the delegate record pda {}",
+            delegate_record.key,
+            record_pda
+        );
+        return Err(EchoError::InvalidDelegate.into());
+    }
+    if record.authority_generation != buffer_header.authority_generation {
+        msg!("Delegate was added by a previous authority of the buffer");
+        return Err(EchoError::InvalidDelegate.into());
+    }
+    let slot = Clock::get()?.slot;
+    if slot > record.expires_at_slot {
+        msg!(
+            "Delegate expired at slot {}, current slot {}",
+            record.expires_at_slot,
+            slot
+        );
+        return Err(EchoError::DelegateExpired.into());
+    }
+    if record.remaining_writes == 0 {
+        msg!("Delegate has no writes left");
+        return Err(EchoError::DelegateQuotaExhausted.into());
+    }
+    record.remaining_writes -= 1;
+    record.pack(&mut delegate_record.try_borrow_mut_data()?)?;
+    Ok(buffer_header)
+}
+
+/// Authorized buffer must be a program owned pda
+/// derived from the seeds stored in its header
+///
+/// returns the buffer header
+pub fn assert_authorized_buffer_pda(
+    program_id: &Pubkey,
+    authorized_buffer: &AccountInfo,
+) -> Result<AuthorizedBufferHeader, ProgramError> {
+    assert_owned_by(authorized_buffer, program_id)?;
+
+    let buffer = authorized_buffer.try_borrow_data()?;
+    check_account_type(&buffer, AccountType::AuthorizedBuffer)?;
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer)?;
+    if buffer.len() <= buffer_header.header_size as usize {
+        msg!("authorized buffer is too small to hold a header");
+        return Err(EchoError::BufferTooSmall.into());
+    }
+
+    assert_program_address(
+        program_id,
+        authorized_buffer,
+        &[
+            AUTHORIZED_BUFFER_SEED,
+            buffer_header.seed_authority.as_ref(),
+            &buffer_header.buffer_seed.to_le_bytes(),
+            &[buffer_header.bump_seed],
+        ],
+    )?;
+    Ok(buffer_header)
+}
+
+/// Echo buffer must be a writable, program owned account
+/// created by `InitializeEchoBuffer`
+pub fn assert_echo_buffer(program_id: &Pubkey, echo_buffer: &AccountInfo) -> ProgramResult {
+    assert_writable(echo_buffer)?;
+    assert_owned_by(echo_buffer, program_id)?;
+
+    let buffer = echo_buffer.try_borrow_data()?;
+    if buffer.len() <= ECHO_BUFFER_HEADER_SIZE {
+        msg!("echo buffer is too small to hold a header");
+        return Err(EchoError::BufferTooSmall.into());
+    }
+    EchoBufferHeader::unpack(&buffer)?;
+    Ok(())
+}
+
+/// Write proposal must be a writable, program owned pda
+/// proposing a write to `authorized_buffer`
+///
+/// returns the proposal header
+pub fn assert_write_proposal(
+    program_id: &Pubkey,
+    authorized_buffer: &AccountInfo,
+    write_proposal: &AccountInfo,
+) -> Result<WriteProposal, ProgramError> {
+    assert_writable(write_proposal)?;
+    assert_owned_by(write_proposal, program_id)?;
+    let proposal = WriteProposal::unpack(&write_proposal.try_borrow_data()?)?;
+    if proposal.buffer != *authorized_buffer.key {
+        msg!("Write proposal does not belong to the buffer");
+        return Err(EchoError::InvalidProposal.into());
+    }
+    assert_program_address(
+        program_id,
+        write_proposal,
+        &[
+            WRITE_PROPOSAL_SEED,
+            authorized_buffer.key.as_ref(),
+            &proposal.data_hash,
+            &[proposal.bump_seed],
+        ],
+    )?;
+    Ok(proposal)
+}
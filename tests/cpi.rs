@@ -0,0 +1,298 @@
+#![cfg(feature = "test-bpf")]
+//! Drives every `xbooth::cpi` helper from a caller program
+//! whose program derived address is the buffer authority
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::{processor, tokio, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use xbooth::cpi;
+use xbooth::state::{
+    find_authorized_buffer_address, AuthorizedBufferHeader, BufferKind, AUTH_BUFFER_HEADER_SIZE,
+};
+
+/// Seed of the caller program pda that owns the buffer
+const CALLER_AUTHORITY_SEED: &[u8] = b"caller";
+
+#[derive(BorshSerialize, BorshDeserialize)]
+enum CallerInstruction {
+    Initialize {
+        buffer_seed: u64,
+        buffer_size: usize,
+    },
+    Echo {
+        data: Vec<u8>,
+    },
+    EchoAt {
+        offset: usize,
+        data: Vec<u8>,
+    },
+    Resize {
+        new_size: usize,
+    },
+    Close,
+    /// fails unless the payload at `offset` is `expected`
+    Read {
+        offset: usize,
+        expected: Vec<u8>,
+    },
+}
+
+/// input accounts:
+/// 1. echo_program
+/// 2. authority: caller pda
+/// 3. authorized_buffer
+/// 4. system_program, or the destination for `Close`
+fn process_caller_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = CallerInstruction::try_from_slice(instruction_data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let accounts_iter = &mut accounts.iter();
+    let echo_program = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    let authorized_buffer = next_account_info(accounts_iter)?;
+    let last_account = next_account_info(accounts_iter)?;
+
+    let (_, bump_seed) = Pubkey::find_program_address(&[CALLER_AUTHORITY_SEED], program_id);
+    let signer_seeds: &[&[&[u8]]] = &[&[CALLER_AUTHORITY_SEED, &[bump_seed]]];
+    let authorized_echo = || cpi::AuthorizedEcho {
+        echo_program: echo_program.clone(),
+        authorized_buffer: authorized_buffer.clone(),
+        authority: authority.clone(),
+    };
+
+    match instruction {
+        CallerInstruction::Initialize {
+            buffer_seed,
+            buffer_size,
+        } => cpi::initialize_authorized_echo(
+            cpi::InitializeAuthorizedEcho {
+                echo_program: echo_program.clone(),
+                authorized_buffer: authorized_buffer.clone(),
+                authority: authority.clone(),
+                system_program: last_account.clone(),
+            },
+            buffer_seed,
+            buffer_size,
+            BufferKind::Overwrite,
+            signer_seeds,
+        ),
+        CallerInstruction::Echo { data } => {
+            cpi::authorized_echo(authorized_echo(), data, signer_seeds)
+        }
+        CallerInstruction::EchoAt { offset, data } => {
+            cpi::authorized_echo_at(authorized_echo(), offset, data, signer_seeds)
+        }
+        CallerInstruction::Resize { new_size } => cpi::resize_authorized_buffer(
+            cpi::ResizeAuthorizedBuffer {
+                echo_program: echo_program.clone(),
+                authorized_buffer: authorized_buffer.clone(),
+                authority: authority.clone(),
+                system_program: last_account.clone(),
+            },
+            new_size,
+            signer_seeds,
+        ),
+        CallerInstruction::Close => cpi::close_authorized_buffer(
+            cpi::CloseAuthorizedBuffer {
+                echo_program: echo_program.clone(),
+                authorized_buffer: authorized_buffer.clone(),
+                authority: authority.clone(),
+                destination: last_account.clone(),
+            },
+            signer_seeds,
+        ),
+        CallerInstruction::Read { offset, expected } => {
+            let data = cpi::read_buffer(
+                cpi::ReadBuffer {
+                    echo_program: echo_program.clone(),
+                    buffer: authorized_buffer.clone(),
+                },
+                offset,
+                expected.len(),
+            )?;
+            if data != expected {
+                msg!("Read {:?}, expected {:?}", data, expected);
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn caller_instruction(
+    caller_program_id: &Pubkey,
+    echo_program_id: &Pubkey,
+    authorized_buffer: &Pubkey,
+    last_account: &Pubkey,
+    instruction: CallerInstruction,
+) -> Instruction {
+    let (authority, _) = Pubkey::find_program_address(&[CALLER_AUTHORITY_SEED], caller_program_id);
+    Instruction {
+        program_id: *caller_program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*echo_program_id, false),
+            AccountMeta::new(authority, false),
+            AccountMeta::new(*authorized_buffer, false),
+            AccountMeta::new(*last_account, false),
+        ],
+        data: instruction.try_to_vec().unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn test_cpi_helpers() {
+    let echo_program_id = Pubkey::new_unique();
+    let caller_program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "caller",
+        caller_program_id,
+        processor!(process_caller_instruction),
+    );
+    program_test.add_program(
+        "xbooth",
+        echo_program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (authority, _) = Pubkey::find_program_address(&[CALLER_AUTHORITY_SEED], &caller_program_id);
+    program_test.add_account(
+        authority,
+        Account::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let buffer_seed: u64 = 1;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&echo_program_id, &authority, buffer_seed);
+    let call = |last_account: &Pubkey, instruction: CallerInstruction| {
+        caller_instruction(
+            &caller_program_id,
+            &echo_program_id,
+            &authorized_buffer,
+            last_account,
+            instruction,
+        )
+    };
+
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            call(
+                &system_program::id(),
+                CallerInstruction::Initialize {
+                    buffer_seed,
+                    buffer_size: AUTH_BUFFER_HEADER_SIZE + 8,
+                },
+            ),
+            call(
+                &system_program::id(),
+                CallerInstruction::Echo {
+                    data: vec![1, 2, 3],
+                },
+            ),
+            call(
+                &system_program::id(),
+                CallerInstruction::EchoAt {
+                    offset: 4,
+                    data: vec![9, 9],
+                },
+            ),
+            call(
+                &system_program::id(),
+                CallerInstruction::Read {
+                    offset: 0,
+                    expected: vec![1, 2, 3, 0, 9, 9, 0, 0],
+                },
+            ),
+            call(
+                &system_program::id(),
+                CallerInstruction::Resize {
+                    new_size: AUTH_BUFFER_HEADER_SIZE + 4,
+                },
+            ),
+            call(
+                &system_program::id(),
+                CallerInstruction::Read {
+                    offset: 1,
+                    expected: vec![2, 3, 0],
+                },
+            ),
+            call(
+                &system_program::id(),
+                CallerInstruction::Read {
+                    offset: 0,
+                    expected: vec![],
+                },
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.authority, authority);
+    assert_eq!(buffer_header.last_writer, authority);
+    assert_eq!(buffer_header.write_seq, 2);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[1, 2, 3, 0]);
+
+    // reads that do not match fail the caller
+    let tx = Transaction::new_signed_with_payer(
+        &[call(
+            &system_program::id(),
+            CallerInstruction::Read {
+                offset: 0,
+                expected: vec![4],
+            },
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert!(matches!(
+        err.unwrap(),
+        TransactionError::InstructionError(0, _)
+    ));
+
+    let destination = Pubkey::new_unique();
+    let tx = Transaction::new_signed_with_payer(
+        &[call(&destination, CallerInstruction::Close)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    assert!(banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .is_none());
+    let destination = banks_client
+        .get_account(destination)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(destination.lamports, buffer.lamports);
+}
@@ -1,276 +1,2996 @@
 #![cfg(feature = "test-bpf")]
 
-use borsh::{BorshDeserialize, BorshSerialize};
-use std::io::Cursor;
-use std::mem;
-
+use borsh::BorshSerialize;
 use solana_program::{
-    account_info::AccountInfo,
-    config::program,
-    instruction::{self, AccountMeta},
-    msg,
-    pubkey::Pubkey,
+    clock::Clock, program::MAX_RETURN_DATA, program_pack::Pack, pubkey::Pubkey, rent::Rent,
     system_instruction, system_program,
 };
-use solana_program_test::ProgramTest;
-use xbooth::instruction::EchoInstruction;
+use solana_program_test::{processor, ProgramTest};
+use xbooth::error::EchoError;
+use xbooth::instruction::{self, EchoMode};
+use xbooth::ring::read_messages;
+use xbooth::state::{
+    auth_buffer_header_size, find_authorized_buffer_address, find_consumer_cursor_address,
+    find_delegate_record_address, find_echo_buffer_address, find_vending_machine_address,
+    find_write_proposal_address, multisig_auth_buffer_header_size, AccountType,
+    AuthorizedBufferHeader, AuthorizedBufferHeaderV0, BufferKind, ConsumerCursor, DelegateRecord,
+    EchoBufferHeader, WriteProposal, AUTH_BUFFER_HEADER_SIZE, CONSUMER_CURSOR_SIZE,
+    DELEGATE_RECORD_SIZE, ECHO_BUFFER_HEADER_SIZE, V1_AUTH_BUFFER_HEADER_SIZE,
+    VENDING_MACHINE_BUFFER_HEADER_SIZE,
+};
 use {
     solana_program_test::*,
+    solana_sdk::account::Account,
+    solana_sdk::instruction::InstructionError,
     solana_sdk::signature::{Keypair, Signer},
-    solana_sdk::transaction::Transaction,
+    solana_sdk::transaction::{Transaction, TransactionError},
 };
 
-// #[tokio::test]
-// async fn test_echo() {
-//     let program_id = Pubkey::new_unique();
-//     let mut program_test = ProgramTest::default();
-//     program_test.add_program("xbooth", program_id, None);
-
-//     let auth = Keypair::new();
-//     program_test.add_account(
-//         auth.pubkey(),
-//         solana_sdk::account::Account {
-//             lamports: 100_000_000_000,
-//             data: vec![],
-//             owner: system_program::id(),
-//             ..solana_sdk::account::Account::default()
-//         },
-//     );
-
-//     let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let echo_account = Keypair::new();
-//     let echo_account_space = 8;
-//     let echo_account_rent = rent.minimum_balance(echo_account_space);
-//     let create_ix = system_instruction::create_account(
-//         &payer.pubkey(),
-//         &echo_account.pubkey(),
-//         echo_account_rent,
-//         echo_account_space as u64,
-//         &program_id,
-//     );
-//     let tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
-//         &[create_ix],
-//         Some(&payer.pubkey()),
-//         &[&payer, &echo_account],
-//         recent_blockhash,
-//     );
-
-//     banks_client.process_transaction(tx).await.unwrap();
-
-//     let transaction_ix: Vec<u8> = vec![0; mem::size_of::<u8>()];
-//     let mut echo_data: Vec<u8> = vec![3; 10];
-//     let mut echo_data_size: Vec<u8> = vec![echo_data.len() as u8, 0, 0, 0];
-//     let mut echo_ix_data = transaction_ix.clone();
-//     echo_ix_data.append(&mut echo_data_size);
-//     echo_ix_data.append(&mut echo_data);
-
-//     let echo_accountmeta = instruction::AccountMeta {
-//         pubkey: echo_account.pubkey(),
-//         is_signer: false,
-//         is_writable: true,
-//     };
-
-//     let accounts = vec![echo_accountmeta];
-//     let echo_ix = instruction::Instruction {
-//         program_id: program_id,
-//         data: echo_ix_data,
-//         accounts,
-//     };
-
-//     let tx2 = solana_sdk::transaction::Transaction::new_signed_with_payer(
-//         &[echo_ix],
-//         Some(&payer.pubkey()),
-//         &[&payer],
-//         recent_blockhash,
-//     );
-
-//     banks_client.process_transaction(tx2).await.unwrap();
-// }
-
-// #[tokio::test]
-// async fn test_initialize_authorize_echo() {
-//     let program_id = Pubkey::new_unique();
-//     let mut program_test = ProgramTest::default();
-//     program_test.add_program("xbooth", program_id, None);
-//     let auth = Keypair::new();
-//     program_test.add_account(
-//         auth.pubkey(),
-//         solana_sdk::account::Account {
-//             lamports: 100_000_000_000,
-//             data: vec![],
-//             owner: system_program::id(),
-//             ..solana_sdk::account::Account::default()
-//         },
-//     );
-//     let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
-
-//     let authority = instruction::AccountMeta {
-//         pubkey: auth.pubkey(),
-//         is_signer: true,
-//         is_writable: false,
-//     };
-
-//     let buffer_seed = 10_u64.to_le_bytes();
-
-//     let (authorized_buffer_key, bump) = Pubkey::find_program_address(
-//         &[b"authority", auth.pubkey().as_ref(), &buffer_seed],
-//         &program_id,
-//     );
-
-//     let authorized_buffer = instruction::AccountMeta {
-//         pubkey: authorized_buffer_key,
-//         is_signer: false,
-//         is_writable: true,
-//     };
-
-//     let system_program_account = instruction::AccountMeta {
-//         pubkey: system_program::id(),
-//         is_signer: false,
-//         is_writable: false,
-//     };
-
-//     let accounts = vec![
-//         authorized_buffer.clone(),
-//         authority.clone(),
-//         system_program_account.clone(),
-//     ];
-
-//     let mut data_input = vec![1; mem::size_of::<u8>()];
-//     let mut buffer_seed_i = vec![0; mem::size_of::<u64>()];
-//     buffer_seed_i[0] = 10;
-//     let mut buffer_size_i = vec![0; mem::size_of::<u64>()];
-//     buffer_size_i[0] = 100;
-//     data_input.append(&mut buffer_seed_i);
-//     data_input.append(&mut buffer_size_i);
-
-//     println!("data_input: {:?}", data_input);
-//     let initialize_authorized_echo_ix = instruction::Instruction {
-//         program_id: program_id,
-//         data: data_input,
-//         accounts,
-//     };
-
-//     let tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
-//         &[initialize_authorized_echo_ix],
-//         Some(&auth.pubkey()),
-//         &[&auth],
-//         recent_blockhash,
-//     );
-//     banks_client.process_transaction(tx).await.unwrap();
-
-//     // try to change the content of the buffer
-//     let instruction_vec = vec![2; mem::size_of::<u8>()];
-//     let new_buffer_data = vec![1; mem::size_of::<u64>()];
-//     let new_buffer_data_length: Vec<u8> = vec![new_buffer_data.len() as u8, 0, 0, 0];
-//     let buffer_data = [
-//         &instruction_vec[..],
-//         &new_buffer_data_length[..],
-//         &new_buffer_data[..],
-//     ]
-//     .concat();
-//     let update_accounts = vec![authorized_buffer.clone(), authority.clone()];
-
-//     let update_buffer_ix = instruction::Instruction {
-//         program_id: program_id,
-//         data: buffer_data,
-//         accounts: update_accounts,
-//     };
-
-//     let update_tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
-//         &[update_buffer_ix],
-//         Some(&auth.pubkey()),
-//         &[&auth],
-//         recent_blockhash,
-//     );
-
-//     banks_client.process_transaction(update_tx).await.unwrap();
-// }
+async fn create_funded_keypair(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: solana_sdk::hash::Hash,
+) -> Keypair {
+    let keypair = Keypair::new();
+    let fund_tx = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &keypair.pubkey(),
+            1_000_000_000,
+        )],
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(fund_tx).await.unwrap();
+    keypair
+}
+
+/// Simulates `tx` and returns the return data it leaves, which has to be set by `program_id`
+async fn simulate_return_data(
+    banks_client: &mut BanksClient,
+    program_id: &Pubkey,
+    tx: Transaction,
+) -> Vec<u8> {
+    let simulation = banks_client.simulate_transaction(tx).await.unwrap();
+    simulation.result.unwrap().unwrap();
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, *program_id);
+    return_data.data
+}
+
+async fn create_mint(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: solana_sdk::hash::Hash,
+) -> Keypair {
+    let rent = banks_client.get_rent().await.unwrap();
+    let mint = Keypair::new();
+    let create_mint_ixs = vec![
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &payer.pubkey(),
+            None,
+            0,
+        )
+        .unwrap(),
+    ];
+    let create_mint_tx = Transaction::new_signed_with_payer(
+        &create_mint_ixs,
+        Some(&payer.pubkey()),
+        &[payer, &mint],
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(create_mint_tx)
+        .await
+        .unwrap();
+    mint
+}
 
 #[tokio::test]
-async fn test_vending_machine_buffer() {
-    // initialize test
+async fn test_echo() {
     let program_id = Pubkey::new_unique();
-    let mut program_test = ProgramTest::new("xbooth", program_id, None);
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let echo_account = Keypair::new();
+    let echo_account_space = 8;
+    let create_ix = instruction::initialize_echo_buffer(
+        &program_id,
+        &echo_account.pubkey(),
+        &payer.pubkey(),
+        ECHO_BUFFER_HEADER_SIZE + echo_account_space,
+    );
+    let echo_ix = instruction::echo(
+        &program_id,
+        &echo_account.pubkey(),
+        vec![3; 10],
+        EchoMode::Truncate,
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[create_ix, echo_ix],
+        Some(&payer.pubkey()),
+        &[&payer, &echo_account],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let echo_buffer = banks_client
+        .get_account(echo_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&echo_buffer.data[ECHO_BUFFER_HEADER_SIZE..], &[3; 8]);
 
-    let auth = Keypair::new();
+    // the written length is returned, truncated to the buffer or as given
+    for (data, mode, written) in [
+        (vec![5; 10], EchoMode::Truncate, 8u64),
+        (vec![5; 3], EchoMode::ZeroPad, 3u64),
+    ] {
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction::echo(
+                &program_id,
+                &echo_account.pubkey(),
+                data,
+                mode,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            recent_blockhash,
+        );
+        assert_eq!(
+            simulate_return_data(&mut banks_client, &program_id, tx).await,
+            written.to_le_bytes()
+        );
+    }
 
-    let vending_machine_mint_keys = Keypair::new();
-    program_test.add_account(
-        auth.pubkey(),
-        solana_sdk::account::Account {
-            lamports: 100_000_000_000,
-            data: vec![],
-            owner: system_program::id(),
-            ..solana_sdk::account::Account::default()
-        },
+    // shorter data leaves the rest untouched or zeroes it
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::echo(
+                &program_id,
+                &echo_account.pubkey(),
+                vec![1; 4],
+                EchoMode::ZeroPad,
+            ),
+            instruction::echo(
+                &program_id,
+                &echo_account.pubkey(),
+                vec![2; 2],
+                EchoMode::Truncate,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let echo_buffer = banks_client
+        .get_account(echo_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &echo_buffer.data[ECHO_BUFFER_HEADER_SIZE..],
+        &[2, 2, 1, 1, 0, 0, 0, 0]
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::echo(
+            &program_id,
+            &echo_account.pubkey(),
+            vec![1; 9],
+            EchoMode::Strict,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::DataLengthMismatch as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_initialize_echo_buffer_pda() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
     );
     let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let rent = banks_client.get_rent().await.unwrap();
 
-    // set up accounts
-    let price: u64 = 101000;
-    let (vending_machine_buffer_pubkey, bump) = Pubkey::find_program_address(
+    let buffer_seed: u64 = 19;
+    let (echo_buffer, _) = find_echo_buffer_address(&program_id, &payer.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
         &[
-            b"vending_machine",
-            vending_machine_mint_keys.pubkey().as_ref(),
-            &price.to_le_bytes(),
+            instruction::initialize_echo_buffer_pda(
+                &program_id,
+                &payer.pubkey(),
+                buffer_seed,
+                ECHO_BUFFER_HEADER_SIZE + 4,
+            ),
+            instruction::echo(
+                &program_id,
+                &echo_buffer,
+                vec![1, 2, 3, 4],
+                EchoMode::Strict,
+            ),
         ],
-        &program_id,
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
     );
+    banks_client.process_transaction(tx).await.unwrap();
 
-    let vending_machine_buffer = AccountMeta {
-        pubkey: vending_machine_buffer_pubkey,
-        is_signer: false,
-        is_writable: true,
-    };
-    let vending_machine_mint = AccountMeta {
-        pubkey: vending_machine_mint_keys.pubkey(),
-        is_signer: false,
-        is_writable: false,
-    };
-
-    let payer = AccountMeta {
-        pubkey: auth.pubkey(),
-        is_signer: true,
-        is_writable: false,
-    };
-
-    let system_program_account = AccountMeta {
-        pubkey: system_program::id(),
-        is_signer: false,
-        is_writable: false,
-    };
-    let accounts = vec![
-        vending_machine_buffer.clone(),
-        vending_machine_mint.clone(),
-        payer.clone(),
-        system_program_account.clone(),
-    ];
+    let buffer = banks_client
+        .get_account(echo_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(buffer.owner, program_id);
+    EchoBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(&buffer.data[ECHO_BUFFER_HEADER_SIZE..], &[1, 2, 3, 4]);
+
+    // accounts created outside the program are not echo buffers
+    let raw_account = Keypair::new();
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &raw_account.pubkey(),
+                rent.minimum_balance(8),
+                8,
+                &program_id,
+            ),
+            instruction::echo(
+                &program_id,
+                &raw_account.pubkey(),
+                vec![1; 8],
+                EchoMode::Truncate,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &raw_account],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(EchoError::InvalidAccountType as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_initialize_authorize_echo() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 10;
+    let buffer_size = AUTH_BUFFER_HEADER_SIZE + 100;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
 
-    // instruction input
-    let data_input: Vec<u8> = vec![3; mem::size_of::<u8>()];
-    let price_data = price.to_le_bytes().to_vec();
-    let buffer_size = 100_u64.to_le_bytes().to_vec();
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                buffer_size,
+                BufferKind::Overwrite,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![1; 8],
+            ),
+        ],
+        Some(&authority.pubkey()),
+        &[&authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
 
-    let input_data: Vec<u8> = [&data_input[..], &price_data[..], &buffer_size[..]].concat();
-    println!("input data {:?}", input_data);
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(buffer.data.len(), buffer_size);
+    assert_eq!(
+        &buffer.data[AUTH_BUFFER_HEADER_SIZE..AUTH_BUFFER_HEADER_SIZE + 8],
+        &[1; 8]
+    );
+    assert!(buffer.data[AUTH_BUFFER_HEADER_SIZE + 8..]
+        .iter()
+        .all(|b| *b == 0));
+}
 
-    let mint_init_ix = instruction::Instruction {
-        program_id: program_id,
-        accounts: accounts,
-        data: input_data,
-    };
-    println!("create tx");
+#[tokio::test]
+async fn test_vending_machine_buffer() {
+    // initialize test
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let auth = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let vending_machine_mint = create_mint(&mut banks_client, &payer, recent_blockhash).await;
 
-    let init_mint_tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
-        &[mint_init_ix],
+    let price: u64 = 101000;
+    let init_mint_tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_vending_machine(
+            &program_id,
+            &vending_machine_mint.pubkey(),
+            &auth.pubkey(),
+            price,
+            100,
+        )],
         Some(&auth.pubkey()),
         &[&auth],
         recent_blockhash,
     );
-    println!("bank client process transaction");
     banks_client
         .process_transaction(init_mint_tx)
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn test_vending_machine_echo() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let rent = banks_client.get_rent().await.unwrap();
+    let mint = create_mint(&mut banks_client, &payer, recent_blockhash).await;
+
+    // create a token account holding tokens
+    let user = Keypair::new();
+    let user_token_account = Keypair::new();
+    let price: u64 = 10;
+    let setup_ixs = vec![
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &user_token_account.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &user_token_account.pubkey(),
+            &mint.pubkey(),
+            &user.pubkey(),
+        )
+        .unwrap(),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &user_token_account.pubkey(),
+            &payer.pubkey(),
+            &[],
+            price + 5,
+        )
+        .unwrap(),
+    ];
+    let setup_tx = Transaction::new_signed_with_payer(
+        &setup_ixs,
+        Some(&payer.pubkey()),
+        &[&payer, &user_token_account],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(setup_tx).await.unwrap();
+
+    // initialize the vending machine buffer and burn tokens to write to it
+    let (vending_machine_buffer, _) =
+        find_vending_machine_address(&program_id, &mint.pubkey(), price);
+    let buffer_size = 20;
+    let echo_data = vec![7; 4];
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_vending_machine(
+                &program_id,
+                &mint.pubkey(),
+                &payer.pubkey(),
+                price,
+                buffer_size,
+            ),
+            instruction::vending_machine_echo(
+                &program_id,
+                &vending_machine_buffer,
+                &mint.pubkey(),
+                &user.pubkey(),
+                &user_token_account.pubkey(),
+                echo_data.clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &user],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(vending_machine_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let header_size = VENDING_MACHINE_BUFFER_HEADER_SIZE;
+    assert_eq!(buffer.data.len(), buffer_size);
+    assert_eq!(&buffer.data[header_size..header_size + 4], &echo_data[..]);
+    assert!(buffer.data[header_size + 4..].iter().all(|b| *b == 0));
+
+    let token_account = banks_client
+        .get_account(user_token_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    let token_account = spl_token::state::Account::unpack(&token_account.data).unwrap();
+    assert_eq!(token_account.amount, 5);
+}
+
+#[tokio::test]
+async fn test_authorized_echo_buffer_too_small() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_authorized_echo(
+            &program_id,
+            &payer.pubkey(),
+            1,
+            AUTH_BUFFER_HEADER_SIZE,
+            BufferKind::Overwrite,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::BufferTooSmall as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_authorized_echo_requires_authority_signature() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 7;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_authorized_echo(
+            &program_id,
+            &authority.pubkey(),
+            buffer_seed,
+            AUTH_BUFFER_HEADER_SIZE + 32,
+            BufferKind::Overwrite,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // the authority key alone is not enough, it has to sign
+    let mut echo_ix = instruction::authorized_echo(
+        &program_id,
+        &authorized_buffer,
+        &authority.pubkey(),
+        vec![1; 8],
+    );
+    echo_ix.accounts[1].is_signer = false;
+    let tx = Transaction::new_signed_with_payer(
+        &[echo_ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::MissingSigner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_close_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let destination = Pubkey::new_unique();
+
+    let buffer_seed: u64 = 3;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_authorized_echo(
+            &program_id,
+            &authority.pubkey(),
+            buffer_seed,
+            AUTH_BUFFER_HEADER_SIZE + 64,
+            BufferKind::Overwrite,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+    let buffer_lamports = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap()
+        .lamports;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::close_authorized_buffer(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            &destination,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    assert!(banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        banks_client.get_balance(destination).await.unwrap(),
+        buffer_lamports
+    );
+}
+
+#[tokio::test]
+async fn test_resize_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let rent = banks_client.get_rent().await.unwrap();
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 4;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let data = vec![5; 16];
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 16,
+                BufferKind::Overwrite,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                data.clone(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    for new_size in [AUTH_BUFFER_HEADER_SIZE + 200, AUTH_BUFFER_HEADER_SIZE + 8] {
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction::resize_authorized_buffer(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                new_size,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer, &authority],
+            recent_blockhash,
+        );
+        banks_client.process_transaction(tx).await.unwrap();
+
+        let buffer = banks_client
+            .get_account(authorized_buffer)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(buffer.data.len(), new_size);
+        assert_eq!(buffer.lamports, rent.minimum_balance(new_size));
+        assert_eq!(
+            &buffer.data[AUTH_BUFFER_HEADER_SIZE..AUTH_BUFFER_HEADER_SIZE + 8],
+            &data[..8]
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_transfer_authority() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let new_authority = Keypair::new();
+
+    let buffer_seed: u64 = 5;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 8,
+                BufferKind::Overwrite,
+            ),
+            instruction::propose_authority(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &new_authority.pubkey(),
+            ),
+            instruction::accept_authority(&program_id, &authorized_buffer, &new_authority.pubkey()),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &new_authority.pubkey(),
+                vec![2; 8],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &new_authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // the previous authority can no longer write
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3; 8],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidAuthority as u32)
+        )
+    );
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[2; 8]);
+}
+
+#[tokio::test]
+async fn test_authorized_echo_at() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 6;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 8,
+                BufferKind::Overwrite,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![1; 8],
+            ),
+            instruction::authorized_echo_at(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                2,
+                vec![9; 3],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &buffer.data[AUTH_BUFFER_HEADER_SIZE..],
+        &[1, 1, 9, 9, 9, 1, 1, 1]
+    );
+
+    // writes past the end of the buffer are rejected
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo_at(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            6,
+            vec![9; 3],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::WriteOutOfBounds as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_append_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 8;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 6,
+                BufferKind::Append,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![1, 2],
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![3, 4, 5],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.cursor, 5);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[1, 2, 3, 4, 5, 0]);
+
+    // appends past the capacity are rejected
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![6, 7],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::BufferFull as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_ring_buffer_consumer() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let consumer = Keypair::new();
+
+    let buffer_seed: u64 = 9;
+    let (ring_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let mut ixs = vec![instruction::initialize_authorized_echo(
+        &program_id,
+        &authority.pubkey(),
+        buffer_seed,
+        AUTH_BUFFER_HEADER_SIZE + 20,
+        BufferKind::Ring,
+    )];
+    for message in [b"aaaaa", b"bbbbb", b"ccccc", b"ddddd"] {
+        ixs.push(instruction::authorized_echo(
+            &program_id,
+            &ring_buffer,
+            &authority.pubkey(),
+            message.to_vec(),
+        ));
+    }
+    ixs.push(instruction::register_consumer(
+        &program_id,
+        &ring_buffer,
+        &authority.pubkey(),
+        &consumer.pubkey(),
+    ));
+    ixs.push(instruction::consume(
+        &program_id,
+        &ring_buffer,
+        &consumer.pubkey(),
+        2,
+    ));
+    let tx = Transaction::new_signed_with_payer(
+        &ixs,
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &consumer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(ring_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(
+        read_messages(&buffer_header, &buffer.data[AUTH_BUFFER_HEADER_SIZE..]),
+        vec![(2, b"ccccc".to_vec()), (3, b"ddddd".to_vec())]
+    );
+
+    let (consumer_cursor, _) =
+        find_consumer_cursor_address(&program_id, &ring_buffer, &consumer.pubkey());
+    let cursor = banks_client
+        .get_account(consumer_cursor)
+        .await
+        .unwrap()
+        .unwrap();
+    let cursor = ConsumerCursor::unpack(&cursor.data).unwrap();
+    assert_eq!(cursor.cursor, 3);
+
+    // consumers can not move backwards
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::consume(
+            &program_id,
+            &ring_buffer,
+            &consumer.pubkey(),
+            1,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &consumer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidSequence as u32)
+        )
+    );
+
+    // only the consumer or the buffer authority can close the cursor
+    let destination = Pubkey::new_unique();
+    let stranger = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::close_consumer(
+            &program_id,
+            &ring_buffer,
+            &stranger.pubkey(),
+            &consumer.pubkey(),
+            &destination,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &stranger],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidAuthority as u32)
+        )
+    );
+
+    // the buffer authority closes the cursor of any consumer, the consumer
+    // still reclaims its cursor after the buffer is closed
+    let other_consumer = Pubkey::new_unique();
+    let cursor_lamports = Rent::default().minimum_balance(CONSUMER_CURSOR_SIZE);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::register_consumer(
+                &program_id,
+                &ring_buffer,
+                &authority.pubkey(),
+                &other_consumer,
+            ),
+            instruction::close_consumer(
+                &program_id,
+                &ring_buffer,
+                &authority.pubkey(),
+                &other_consumer,
+                &destination,
+            ),
+            instruction::close_authorized_buffer(
+                &program_id,
+                &ring_buffer,
+                &authority.pubkey(),
+                &authority.pubkey(),
+            ),
+            instruction::close_consumer(
+                &program_id,
+                &ring_buffer,
+                &consumer.pubkey(),
+                &consumer.pubkey(),
+                &destination,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &consumer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    for consumer in [consumer.pubkey(), other_consumer] {
+        let (consumer_cursor, _) =
+            find_consumer_cursor_address(&program_id, &ring_buffer, &consumer);
+        assert!(banks_client
+            .get_account(consumer_cursor)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    let destination = banks_client
+        .get_account(destination)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(destination.lamports, 2 * cursor_lamports);
+}
+
+#[tokio::test]
+async fn test_consume_rejects_forged_cursor() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let authority = Keypair::new();
+    program_test.add_account(
+        authority.pubkey(),
+        Account::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let consumer = Keypair::new();
+    let buffer_seed: u64 = 9;
+    let (ring_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+
+    // cursor owned by the program but not at the consumer cursor pda
+    let forged_cursor = Pubkey::new_unique();
+    let (_, bump_seed) =
+        find_consumer_cursor_address(&program_id, &ring_buffer, &consumer.pubkey());
+    let mut cursor_data = vec![0; CONSUMER_CURSOR_SIZE];
+    ConsumerCursor {
+        account_type: AccountType::ConsumerCursor,
+        version: AccountType::ConsumerCursor.current_version(),
+        bump_seed,
+        buffer: ring_buffer,
+        consumer: consumer.pubkey(),
+        cursor: 0,
+    }
+    .pack(&mut cursor_data)
+    .unwrap();
+    program_test.add_account(
+        forged_cursor,
+        Account {
+            lamports: Rent::default().minimum_balance(cursor_data.len()),
+            data: cursor_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut consume = instruction::consume(&program_id, &ring_buffer, &consumer.pubkey(), 0);
+    consume.accounts[1].pubkey = forged_cursor;
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 20,
+                BufferKind::Ring,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &ring_buffer,
+                &authority.pubkey(),
+                b"aaaaa".to_vec(),
+            ),
+            consume,
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &consumer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(EchoError::InvalidBufferPda as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_v0_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let authority = Keypair::new();
+    program_test.add_account(
+        authority.pubkey(),
+        Account::new(1_000_000_000, 0, &system_program::id()),
+    );
+
+    // buffer as written by the untagged v0 program
+    let buffer_seed: u64 = 11;
+    let (authorized_buffer, bump_seed) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let mut v0_data = AuthorizedBufferHeaderV0 {
+        bump_seed,
+        buffer_seed,
+    }
+    .try_to_vec()
+    .unwrap();
+    v0_data.extend_from_slice(&[1, 2, 3, 4]);
+    program_test.add_account(
+        authorized_buffer,
+        Account {
+            lamports: Rent::default().minimum_balance(v0_data.len()),
+            data: v0_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // untagged pdas can not be taken over as public echo buffers, they can never sign
+    let mut migrate_as_echo_buffer =
+        instruction::migrate_echo_buffer(&program_id, &authorized_buffer, &payer.pubkey());
+    migrate_as_echo_buffer.accounts[0].is_signer = false;
+    let tx = Transaction::new_signed_with_payer(
+        &[migrate_as_echo_buffer],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::MissingSigner as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::migrate_authorized_buffer(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.account_type, AccountType::AuthorizedBuffer);
+    assert_eq!(
+        buffer_header.version,
+        AccountType::AuthorizedBuffer.current_version()
+    );
+    assert_eq!(buffer_header.bump_seed, bump_seed);
+    assert_eq!(buffer_header.authority, authority.pubkey());
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[1, 2, 3, 4]);
+
+    // migrated buffers can not be migrated again
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![5, 6, 7, 8],
+            ),
+            instruction::migrate_authorized_buffer(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(EchoError::AlreadyMigrated as u32)
+        )
+    );
+
+    // tagged buffers are not accepted as another account type
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::migrate_vending_machine_buffer(
+            &program_id,
+            &authorized_buffer,
+            &Pubkey::new_unique(),
+            &authority.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidAccountType as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![5, 6, 7, 8],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[5, 6, 7, 8]);
+}
+
+#[tokio::test]
+async fn test_read_buffer() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 12;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![1, 2, 3, 4],
+            ),
+            instruction::read_buffer(&program_id, &authorized_buffer, 1, 3),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // reads past the payload are rejected
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::read_buffer(
+            &program_id,
+            &authorized_buffer,
+            2,
+            3,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::ReadOutOfBounds as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::read_buffer(
+            &program_id,
+            &authorized_buffer,
+            0,
+            MAX_RETURN_DATA + 1,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::ReadTooLarge as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_delegated_authorized_echo() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    let authority =
+        create_funded_keypair(&mut context.banks_client, &payer, recent_blockhash).await;
+    let delegate = Keypair::new();
+
+    let buffer_seed: u64 = 20;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let (delegate_record, _) =
+        find_delegate_record_address(&program_id, &authorized_buffer, &delegate.pubkey());
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            instruction::add_delegate(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &delegate.pubkey(),
+                1_000,
+                1,
+            ),
+            instruction::delegated_authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &delegate.pubkey(),
+                vec![1, 2],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &delegate],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[1, 2, 0, 0]);
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    assert_eq!(buffer_header.last_writer, delegate.pubkey());
+    assert_eq!(buffer_header.last_write_slot, clock.slot);
+    assert_eq!(buffer_header.last_write_len, 2);
+    assert!(buffer_header.last_write_timestamp > 0);
+    let record = context
+        .banks_client
+        .get_account(delegate_record)
+        .await
+        .unwrap()
+        .unwrap();
+    let record = DelegateRecord::unpack(&record.data).unwrap();
+    assert_eq!(record.delegate, delegate.pubkey());
+    assert_eq!(record.remaining_writes, 0);
+
+    // the quota is used up
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::delegated_authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &delegate.pubkey(),
+            vec![3],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &delegate],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::DelegateQuotaExhausted as u32)
+        )
+    );
+
+    // delegates have to pass their record
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &delegate.pubkey(),
+            vec![3],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &delegate],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidAuthority as u32)
+        )
+    );
+
+    // adding the delegate again refreshes the quota, until it expires
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::add_delegate(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            &delegate.pubkey(),
+            100,
+            5,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    context.warp_to_slot(200).unwrap();
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::delegated_authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &delegate.pubkey(),
+            vec![3],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &delegate],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::DelegateExpired as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::revoke_delegate(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            &delegate.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    assert!(context
+        .banks_client
+        .get_account(delegate_record)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_delegates_do_not_outlive_the_buffer() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let delegate = Keypair::new();
+
+    let buffer_seed: u64 = 24;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            instruction::add_delegate(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &delegate.pubkey(),
+                1_000,
+                5,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // delegates have to be revoked before the buffer can be closed
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::close_authorized_buffer(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            &authority.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::BufferHasDelegates as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::revoke_delegate(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &delegate.pubkey(),
+            ),
+            instruction::close_authorized_buffer(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // a buffer created again at the same address starts without delegates
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            instruction::delegated_authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &delegate.pubkey(),
+                vec![1],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &delegate],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(EchoError::IncorrectOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_delegates_of_previous_authority() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let new_authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+    let delegate = Keypair::new();
+
+    let buffer_seed: u64 = 25;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let (delegate_record, _) =
+        find_delegate_record_address(&program_id, &authorized_buffer, &delegate.pubkey());
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            instruction::add_delegate(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &delegate.pubkey(),
+                1_000,
+                5,
+            ),
+            instruction::propose_authority(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &new_authority.pubkey(),
+            ),
+            instruction::accept_authority(&program_id, &authorized_buffer, &new_authority.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &new_authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // the delegate was added by the previous authority
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::delegated_authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &delegate.pubkey(),
+            vec![1],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &delegate],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidDelegate as u32)
+        )
+    );
+
+    // until the new authority adds it again
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::add_delegate(
+                &program_id,
+                &authorized_buffer,
+                &new_authority.pubkey(),
+                &delegate.pubkey(),
+                1_000,
+                5,
+            ),
+            instruction::delegated_authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &delegate.pubkey(),
+                vec![2],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &new_authority, &delegate],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.authority_generation, 1);
+    assert_eq!(buffer_header.delegate_count, 1);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[2, 0, 0, 0]);
+    let record = banks_client
+        .get_account(delegate_record)
+        .await
+        .unwrap()
+        .unwrap();
+    let record = DelegateRecord::unpack(&record.data).unwrap();
+    assert_eq!(record.authority_generation, 1);
+    assert_eq!(record.remaining_writes, 4);
+}
+
+#[tokio::test]
+async fn test_delegate_record_must_be_the_pda() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let authority = Keypair::new();
+    program_test.add_account(
+        authority.pubkey(),
+        Account::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let delegate = Keypair::new();
+    let buffer_seed: u64 = 26;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+
+    // record owned by the program but not at the delegate record pda
+    let forged_record = Pubkey::new_unique();
+    let (_, bump_seed) =
+        find_delegate_record_address(&program_id, &authorized_buffer, &delegate.pubkey());
+    let mut record_data = vec![0; DELEGATE_RECORD_SIZE];
+    DelegateRecord {
+        account_type: AccountType::DelegateRecord,
+        version: AccountType::DelegateRecord.current_version(),
+        bump_seed,
+        buffer: authorized_buffer,
+        delegate: delegate.pubkey(),
+        expires_at_slot: u64::MAX,
+        remaining_writes: u64::MAX,
+        authority_generation: 0,
+    }
+    .pack(&mut record_data)
+    .unwrap();
+    program_test.add_account(
+        forged_record,
+        Account {
+            lamports: Rent::default().minimum_balance(record_data.len()),
+            data: record_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut delegated_echo = instruction::delegated_authorized_echo(
+        &program_id,
+        &authorized_buffer,
+        &delegate.pubkey(),
+        vec![1],
+    );
+    delegated_echo.accounts[2].pubkey = forged_record;
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            delegated_echo,
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &delegate],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(EchoError::InvalidDelegate as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_multisig_authorized_echo() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let signers = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let signer_keys: Vec<Pubkey> = signers.iter().map(|signer| signer.pubkey()).collect();
+
+    let buffer_seed: u64 = 21;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &payer.pubkey(), buffer_seed);
+
+    // the header has to fit the signers
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_multisig_echo(
+            &program_id,
+            &payer.pubkey(),
+            buffer_seed,
+            multisig_auth_buffer_header_size(3),
+            BufferKind::Overwrite,
+            2,
+            signer_keys.clone(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::BufferTooSmall as u32)
+        )
+    );
+
+    // thresholds above the number of signers are rejected
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_multisig_echo(
+            &program_id,
+            &payer.pubkey(),
+            buffer_seed,
+            multisig_auth_buffer_header_size(3) + 4,
+            BufferKind::Overwrite,
+            4,
+            signer_keys.clone(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidMultisig as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_multisig_echo(
+            &program_id,
+            &payer.pubkey(),
+            buffer_seed,
+            multisig_auth_buffer_header_size(3) + 4,
+            BufferKind::Overwrite,
+            2,
+            signer_keys.clone(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.threshold, 2);
+    assert_eq!(buffer_header.signers, signer_keys);
+    assert_eq!(buffer_header.seed_authority, payer.pubkey());
+    // only multisig headers reserve space, for their own signers
+    assert_eq!(
+        buffer_header.header_size as usize,
+        multisig_auth_buffer_header_size(3)
+    );
+    assert_eq!(buffer.data.len(), multisig_auth_buffer_header_size(3) + 4);
+
+    // a single signer is not enough
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &signers[0].pubkey(),
+            vec![1, 2],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &signers[0]],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::NotEnoughSigners as u32)
+        )
+    );
+
+    // neither is the payer the buffer is derived from
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::with_co_signers(
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &payer.pubkey(),
+                vec![1, 2],
+            ),
+            &[signers[0].pubkey()],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &signers[0]],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::InvalidAuthority as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::with_co_signers(
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &signers[0].pubkey(),
+                vec![1, 2],
+            ),
+            &[signers[2].pubkey()],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &signers[0], &signers[2]],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &buffer.data[multisig_auth_buffer_header_size(3)..],
+        &[1, 2, 0, 0]
+    );
+
+    // admin instructions need the threshold as well
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::close_authorized_buffer(
+            &program_id,
+            &authorized_buffer,
+            &signers[1].pubkey(),
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &signers[1]],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::NotEnoughSigners as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::with_co_signers(
+            instruction::close_authorized_buffer(
+                &program_id,
+                &authorized_buffer,
+                &signers[1].pubkey(),
+                &payer.pubkey(),
+            ),
+            &[signers[0].pubkey()],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &signers[1], &signers[0]],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+    assert!(banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_migrate_v1_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let authority = Keypair::new();
+
+    // buffer as written before multisig signers were added to the header
+    let buffer_seed: u64 = 22;
+    let (authorized_buffer, bump_seed) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let mut v1_data = vec![0; AUTH_BUFFER_HEADER_SIZE];
+    let mut buffer_header = AuthorizedBufferHeader::new(
+        bump_seed,
+        buffer_seed,
+        BufferKind::Append,
+        authority.pubkey(),
+    );
+    buffer_header.version = 1;
+    buffer_header.cursor = 2;
+    buffer_header.pack(&mut v1_data).unwrap();
+    v1_data.truncate(V1_AUTH_BUFFER_HEADER_SIZE);
+    v1_data.extend_from_slice(&[1, 2, 0, 0]);
+    program_test.add_account(
+        authorized_buffer,
+        Account {
+            lamports: Rent::default().minimum_balance(v1_data.len()),
+            data: v1_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // v1 buffers have to be migrated before they can be written
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::UnsupportedVersion as u32)
+        )
+    );
+
+    // anyone can pay for the migration
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::migrate_authorized_buffer(
+                &program_id,
+                &authorized_buffer,
+                &payer.pubkey(),
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![3],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(
+        buffer_header.version,
+        AccountType::AuthorizedBuffer.current_version()
+    );
+    assert_eq!(buffer_header.authority, authority.pubkey());
+    assert_eq!(buffer_header.cursor, 3);
+    assert_eq!(buffer_header.threshold, 0);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[1, 2, 3, 0]);
+}
+
+/// Starts a program test with an authorized buffer written at layout `version`,
+/// migrates it to the current layout and checks the payload is preserved and the
+/// fields added since `version` are at their defaults
+async fn start_with_migrated_buffer(
+    program_id: Pubkey,
+    version: u8,
+) -> (ProgramTestContext, Keypair, Pubkey) {
+    let mut program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let authority = Keypair::new();
+    program_test.add_account(
+        authority.pubkey(),
+        Account::new(1_000_000_000, 0, &system_program::id()),
+    );
+
+    let buffer_seed: u64 = 23;
+    let (authorized_buffer, bump_seed) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let mut old_data = vec![0; AUTH_BUFFER_HEADER_SIZE];
+    let mut buffer_header = AuthorizedBufferHeader::new(
+        bump_seed,
+        buffer_seed,
+        BufferKind::Overwrite,
+        authority.pubkey(),
+    );
+    buffer_header.version = version;
+    buffer_header.cursor = 2;
+    buffer_header.pack(&mut old_data).unwrap();
+    old_data.resize(auth_buffer_header_size(version).unwrap(), 0);
+    old_data.extend_from_slice(&[1, 2, 0, 0]);
+    program_test.add_account(
+        authorized_buffer,
+        Account {
+            lamports: Rent::default().minimum_balance(old_data.len()),
+            data: old_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::migrate_authorized_buffer(
+            &program_id,
+            &authorized_buffer,
+            &context.payer.pubkey(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(
+        buffer_header.version,
+        AccountType::AuthorizedBuffer.current_version()
+    );
+    assert_eq!(buffer_header.authority, authority.pubkey());
+    assert_eq!(buffer_header.cursor, 2);
+    assert_eq!(buffer_header.write_seq, 0);
+    assert_eq!(buffer_header.last_writer, Pubkey::default());
+    assert!(!buffer_header.frozen);
+    assert_eq!(buffer_header.authority_generation, 0);
+    assert_eq!(buffer_header.delegate_count, 0);
+    assert_eq!(buffer_header.header_size as usize, AUTH_BUFFER_HEADER_SIZE);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[1, 2, 0, 0]);
+
+    (context, authority, authorized_buffer)
+}
+
+#[tokio::test]
+async fn test_migrate_v2_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let (mut context, authority, authorized_buffer) =
+        start_with_migrated_buffer(program_id, 2).await;
+
+    // migrated buffers start counting writes from zero
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo_cas(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3],
+            0,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.write_seq, 1);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[3, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn test_migrate_v3_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let (mut context, authority, authorized_buffer) =
+        start_with_migrated_buffer(program_id, 3).await;
+    context.warp_to_slot(50).unwrap();
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    // the first write after the migration fills in the last write fields
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3, 4, 5],
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.write_seq, 1);
+    assert_eq!(buffer_header.last_writer, authority.pubkey());
+    assert_eq!(buffer_header.last_write_slot, 50);
+    assert_eq!(buffer_header.last_write_len, 3);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[3, 4, 5, 0]);
+}
+
+#[tokio::test]
+async fn test_migrate_v4_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let (mut context, authority, authorized_buffer) =
+        start_with_migrated_buffer(program_id, 4).await;
+
+    // migrated buffers are writable until frozen
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![3],
+            ),
+            instruction::freeze_authorized_buffer(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![4],
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::BufferFrozen as u32)
+        )
+    );
+
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert!(buffer_header.frozen);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[3, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn test_migrate_v5_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let (mut context, authority, authorized_buffer) =
+        start_with_migrated_buffer(program_id, 5).await;
+
+    // migrated buffers start without delegates in the first authority generation
+    let delegate = Keypair::new();
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::add_delegate(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                &delegate.pubkey(),
+                1_000,
+                1,
+            ),
+            instruction::delegated_authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &delegate.pubkey(),
+                vec![3],
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority, &delegate],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.authority_generation, 0);
+    assert_eq!(buffer_header.delegate_count, 1);
+    assert_eq!(buffer_header.last_writer, delegate.pubkey());
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[3, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn test_migrate_v6_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let (mut context, authority, authorized_buffer) =
+        start_with_migrated_buffer(program_id, 6).await;
+
+    // the signer space reserved by v6 headers is given up,
+    // its rent stays in the buffer until it is closed
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(buffer.data.len(), AUTH_BUFFER_HEADER_SIZE + 4);
+    assert_eq!(
+        buffer.lamports,
+        Rent::default().minimum_balance(auth_buffer_header_size(6).unwrap() + 4)
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3],
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[3, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn test_migrate_v0_echo_buffer() {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+
+    // keypair buffer as written by the untagged v0 program, payload only
+    let echo_buffer_keypair = Keypair::new();
+    let echo_buffer = echo_buffer_keypair.pubkey();
+    program_test.add_account(
+        echo_buffer,
+        Account {
+            lamports: Rent::default().minimum_balance(4),
+            data: b"echo".to_vec(),
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::migrate_echo_buffer(&program_id, &echo_buffer, &payer.pubkey()),
+            instruction::echo(&program_id, &echo_buffer, vec![5, 6], EchoMode::Truncate),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &echo_buffer_keypair],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(echo_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = EchoBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(
+        buffer_header.version,
+        AccountType::EchoBuffer.current_version()
+    );
+    assert_eq!(buffer.data.len(), ECHO_BUFFER_HEADER_SIZE + 4);
+    assert_eq!(&buffer.data[ECHO_BUFFER_HEADER_SIZE..], &[5, 6, b'h', b'o']);
+    assert!(buffer.lamports >= Rent::default().minimum_balance(buffer.data.len()));
+
+    // migrated buffers can not be migrated again
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::migrate_echo_buffer(
+            &program_id,
+            &echo_buffer,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &echo_buffer_keypair],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::AlreadyMigrated as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_proposed_multisig_write() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    let proposer = create_funded_keypair(&mut context.banks_client, &payer, recent_blockhash).await;
+    let signers = [
+        Keypair::new(),
+        create_funded_keypair(&mut context.banks_client, &payer, recent_blockhash).await,
+    ];
+
+    let buffer_seed: u64 = 23;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &payer.pubkey(), buffer_seed);
+    let data = vec![1, 2, 3];
+    let data_hash = solana_program::hash::hash(&data).to_bytes();
+    let (write_proposal, _) =
+        find_write_proposal_address(&program_id, &authorized_buffer, &data_hash);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_multisig_echo(
+                &program_id,
+                &payer.pubkey(),
+                buffer_seed,
+                multisig_auth_buffer_header_size(3) + 4,
+                BufferKind::Overwrite,
+                2,
+                vec![proposer.pubkey(), signers[0].pubkey(), signers[1].pubkey()],
+            ),
+            instruction::propose_write(
+                &program_id,
+                &authorized_buffer,
+                &proposer.pubkey(),
+                data.clone(),
+                100,
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &proposer],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // the proposer alone does not meet the threshold
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::execute_write(
+            &program_id,
+            &authorized_buffer,
+            &proposer.pubkey(),
+            &data_hash,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::NotEnoughSigners as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::approve_write(
+            &program_id,
+            &authorized_buffer,
+            &proposer.pubkey(),
+            &data_hash,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &proposer],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::AlreadyApproved as u32)
+        )
+    );
+
+    // approvals are collected in separate transactions, anyone can execute
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::approve_write(
+            &program_id,
+            &authorized_buffer,
+            &signers[1].pubkey(),
+            &data_hash,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &signers[1]],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    let proposal = context
+        .banks_client
+        .get_account(write_proposal)
+        .await
+        .unwrap()
+        .unwrap();
+    let proposal = WriteProposal::unpack(&proposal.data).unwrap();
+    assert_eq!(
+        proposal.approvals,
+        vec![proposer.pubkey(), signers[1].pubkey()]
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::execute_write(
+            &program_id,
+            &authorized_buffer,
+            &proposer.pubkey(),
+            &data_hash,
+        )],
+        Some(&signers[1].pubkey()),
+        &[&signers[1]],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    let buffer = context
+        .banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &buffer.data[multisig_auth_buffer_header_size(3)..],
+        &[1, 2, 3, 0]
+    );
+    assert!(context
+        .banks_client
+        .get_account(write_proposal)
+        .await
+        .unwrap()
+        .is_none());
+
+    // expired proposals can not be approved, only cancelled
+    let data = vec![4, 5, 6];
+    let data_hash = solana_program::hash::hash(&data).to_bytes();
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::propose_write(
+            &program_id,
+            &authorized_buffer,
+            &proposer.pubkey(),
+            data,
+            10,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &proposer],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    context.warp_to_slot(200).unwrap();
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::approve_write(
+            &program_id,
+            &authorized_buffer,
+            &signers[0].pubkey(),
+            &data_hash,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &signers[0]],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::ProposalExpired as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::cancel_write(
+            &program_id,
+            &authorized_buffer,
+            &proposer.pubkey(),
+            &data_hash,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &proposer],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    let (write_proposal, _) =
+        find_write_proposal_address(&program_id, &authorized_buffer, &data_hash);
+    assert!(context
+        .banks_client
+        .get_account(write_proposal)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_authorized_echo_cas() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 24;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            instruction::authorized_echo_cas(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![1],
+                0,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![2],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // a writer that read the buffer before the last write is rejected
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo_cas(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3],
+            1,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EchoError::WriteSequenceMismatch as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::authorized_echo_cas(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3],
+            2,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    // the new write sequence is returned
+    assert_eq!(
+        simulate_return_data(&mut banks_client, &program_id, tx.clone()).await,
+        3u64.to_le_bytes()
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert_eq!(buffer_header.write_seq, 3);
+    assert_eq!(buffer_header.last_writer, authority.pubkey());
+    assert_eq!(buffer_header.last_write_len, 1);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[3, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn test_freeze_authorized_buffer() {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "xbooth",
+        program_id,
+        processor!(xbooth::processor::Processor::process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let authority = create_funded_keypair(&mut banks_client, &payer, recent_blockhash).await;
+
+    let buffer_seed: u64 = 25;
+    let (authorized_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_authorized_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                BufferKind::Overwrite,
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+                vec![1, 2],
+            ),
+            instruction::freeze_authorized_buffer(
+                &program_id,
+                &authorized_buffer,
+                &authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // every write path is rejected
+    for ix in [
+        instruction::authorized_echo(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            vec![3],
+        ),
+        instruction::authorized_echo_at(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            1,
+            vec![3],
+        ),
+        instruction::resize_authorized_buffer(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            AUTH_BUFFER_HEADER_SIZE + 8,
+        ),
+        instruction::close_authorized_buffer(
+            &program_id,
+            &authorized_buffer,
+            &authority.pubkey(),
+            &authority.pubkey(),
+        ),
+    ] {
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&payer.pubkey()),
+            &[&payer, &authority],
+            recent_blockhash,
+        );
+        let err = banks_client.process_transaction(tx).await.unwrap_err();
+        assert_eq!(
+            err.unwrap(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(EchoError::BufferFrozen as u32)
+            )
+        );
+    }
+    let buffer = banks_client
+        .get_account(authorized_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[1, 2, 0, 0]);
+
+    // write-once buffers are frozen from the start
+    let buffer_seed: u64 = 26;
+    let (immutable_buffer, _) =
+        find_authorized_buffer_address(&program_id, &authority.pubkey(), buffer_seed);
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_immutable_echo(
+                &program_id,
+                &authority.pubkey(),
+                buffer_seed,
+                AUTH_BUFFER_HEADER_SIZE + 4,
+                vec![4, 5, 6],
+            ),
+            instruction::authorized_echo(
+                &program_id,
+                &immutable_buffer,
+                &authority.pubkey(),
+                vec![7],
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    let err = banks_client.process_transaction(tx).await.unwrap_err();
+    assert_eq!(
+        err.unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(EchoError::BufferFrozen as u32)
+        )
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction::initialize_immutable_echo(
+            &program_id,
+            &authority.pubkey(),
+            buffer_seed,
+            AUTH_BUFFER_HEADER_SIZE + 4,
+            vec![4, 5, 6],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+    let buffer = banks_client
+        .get_account(immutable_buffer)
+        .await
+        .unwrap()
+        .unwrap();
+    let buffer_header = AuthorizedBufferHeader::unpack(&buffer.data).unwrap();
+    assert!(buffer_header.frozen);
+    assert_eq!(buffer_header.write_seq, 1);
+    assert_eq!(&buffer.data[AUTH_BUFFER_HEADER_SIZE..], &[4, 5, 6, 0]);
+}